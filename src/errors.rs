@@ -1,5 +1,28 @@
+use bitcoin::hashes::{sha256, Hash};
+use std::sync::atomic::{AtomicBool, Ordering};
 use thiserror::Error;
 
+static REDACT_KEYS: AtomicBool = AtomicBool::new(false);
+
+/// Sets whether error messages name keys by a stable hash instead of the key itself.
+///
+/// Applies process-wide, since messages are formatted without access to a client. Only
+/// the message is affected: structured fields such as `DecodeError::key` keep the real key.
+pub fn set_redact_keys_in_errors(redact: bool) {
+    REDACT_KEYS.store(redact, Ordering::Relaxed);
+}
+
+/// Formats a key for an error message, as `redacted-` followed by the first 16 hex digits
+/// of its SHA-256 hash when redaction is on
+pub(crate) fn display_key(key: &str) -> String {
+    if REDACT_KEYS.load(Ordering::Relaxed) {
+        let hash = sha256::Hash::hash(key.as_bytes()).to_string();
+        format!("redacted-{}", &hash[..16])
+    } else {
+        key.to_string()
+    }
+}
+
 #[derive(Error, Debug, uniffi::Error)]
 pub enum VssError {
     #[error("Connection error: {error_details}")]
@@ -11,6 +34,18 @@ pub enum VssError {
     #[error("Store error: {error_details}")]
     StoreError { error_details: String },
 
+    /// A conditional write lost against a concurrent change. `current_version` is the
+    /// server's version of the key (0 if it doesn't exist), looked up after the conflict
+    /// for writes to a single key such as `update`, and None when it isn't known.
+    /// `conflicting_keys` lists the keys of a batched conditional write whose versions
+    /// didn't match, and is empty when they aren't known.
+    #[error("Conflict error: {error_details}")]
+    ConflictError {
+        error_details: String,
+        current_version: Option<i64>,
+        conflicting_keys: Vec<String>,
+    },
+
     #[error("Get error: {error_details}")]
     GetError { error_details: String },
 
@@ -26,9 +61,81 @@ pub enum VssError {
     #[error("Invalid data: {error_details}")]
     InvalidData { error_details: String },
 
+    /// A value was fetched but isn't a valid stored envelope, so it was corrupted or
+    /// written by something other than this client. Retrying won't help.
+    #[error("Failed to decode stored value for key '{}'", display_key(.key))]
+    DecodeError { key: String },
+
+    /// A value decrypted but didn't match the checksum stored with it, so it was corrupted
+    /// before encryption or truncated. Only values written with `verify_integrity` carry a
+    /// checksum. Retrying won't help.
+    #[error("Integrity check failed for key '{}'", display_key(.key))]
+    IntegrityError { key: String },
+
+    /// A value was written with encryption and read by a client without it, or the other
+    /// way around. Retrying won't help.
+    #[error("Encryption mismatch for key '{}': the value was written by a client with different encryption settings, with or without a seed", display_key(.key))]
+    EncryptionMismatch { key: String },
+
+    /// A value couldn't be decrypted with this client's keys, so it was encrypted with a
+    /// different seed or HKDF salts, or its ciphertext was corrupted. Retrying won't help.
+    #[error("Failed to decrypt stored value for key '{}' with this client's keys", display_key(.key))]
+    DecryptError { key: String },
+
+    /// The server could not be reached or failed to respond. `attempts` and `elapsed_ms`
+    /// report how many tries were made and how long they took in total, including backoff.
     #[error("Network error: {error_details}")]
-    NetworkError { error_details: String },
+    NetworkError {
+        error_details: String,
+        attempts: Option<u32>,
+        elapsed_ms: Option<u64>,
+    },
+
+    /// The server did not respond within the client's `read_timeout_ms`. A server that
+    /// can't be connected to within `connect_timeout_ms` fails with `ConnectionError`.
+    #[error("Timeout error: {error_details}")]
+    TimeoutError { error_details: String },
+
+    /// The server rejected requests for exceeding its rate limit (HTTP 429), even after
+    /// retries. `attempts` and `elapsed_ms` are reported as for `NetworkError`.
+    /// `retry_after_ms` is always None for now: `vss-client` doesn't expose the response
+    /// headers, so the server's `Retry-After` value can't be read.
+    ///
+    /// The status is only visible when the 429 response has no VSS `ErrorResponse` body.
+    /// A 429 with such a body is reported by the error code in it, typically as
+    /// `NetworkError`, which is retryable as well.
+    #[error("Rate limited by the server")]
+    RateLimited {
+        retry_after_ms: Option<u64>,
+        attempts: Option<u32>,
+        elapsed_ms: Option<u64>,
+    },
+
+    /// A batch operation stopped because its `VssCancelHandle` was cancelled. Transactions
+    /// committed before the cancellation was noticed are kept.
+    #[error("Operation cancelled")]
+    Cancelled,
+
+    /// The crate or the server cannot provide the requested capability.
+    #[error("Unsupported: {feature}")]
+    Unsupported { feature: String },
 
     #[error("Unknown error: {error_details}")]
     UnknownError { error_details: String },
 }
+
+impl VssError {
+    /// Whether retrying the failed operation may succeed.
+    ///
+    /// Matches the classification the client's own retry policy uses: server and network
+    /// failures, timeouts and rate limits are transient, while missing keys, invalid requests,
+    /// conflicts, auth failures and unsupported endpoints won't change by trying again.
+    /// Neither will `UnknownError`, which reports responses the client couldn't make sense
+    /// of and failures within the client itself.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            VssError::NetworkError { .. } | VssError::TimeoutError { .. } | VssError::RateLimited { .. }
+        )
+    }
+}
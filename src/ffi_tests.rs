@@ -1,13 +1,18 @@
 #[cfg(test)]
+#[allow(clippy::module_inception)]
 mod ffi_tests {
     use crate::*;
     
     // Unit tests for the FFI interface
     const MOCK_BASE_URL: &str = "https://vss.example.com";
     const TEST_STORE_ID: &str = "test-store-ffi";
+
+    // Serializes tests that install or shut down the global client
+    static GLOBAL_CLIENT: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
     
     #[tokio::test]
     async fn test_ffi_client_lifecycle() {
+        let _guard = GLOBAL_CLIENT.lock().await;
         // Test that we can create and shutdown client without errors
         let result = vss_new_client(
             MOCK_BASE_URL.to_string(),
@@ -15,13 +20,16 @@ mod ffi_tests {
         ).await;
         
         assert!(result.is_ok());
+        assert!(vss_is_initialized());
         
         // Shutdown client
         vss_shutdown_client();
+        assert!(!vss_is_initialized());
     }
     
     #[tokio::test]
     async fn test_ffi_client() {
+        let _guard = GLOBAL_CLIENT.lock().await;
         let result = vss_new_client(
             MOCK_BASE_URL.to_string(),
             TEST_STORE_ID.to_string()
@@ -33,6 +41,7 @@ mod ffi_tests {
     
     #[tokio::test]
     async fn test_ffi_error_no_client() {
+        let _guard = GLOBAL_CLIENT.lock().await;
         // Don't initialize client, should get error
         let result = vss_get("any-key".to_string()).await;
         
@@ -47,6 +56,7 @@ mod ffi_tests {
     
     #[tokio::test]
     async fn test_ffi_client_reinitialize() {
+        let _guard = GLOBAL_CLIENT.lock().await;
         // Test that we can create, shutdown, and recreate client
         vss_new_client(
             MOCK_BASE_URL.to_string(),
@@ -65,6 +75,259 @@ mod ffi_tests {
         vss_shutdown_client();
     }
     
+    #[tokio::test]
+    async fn test_ffi_rotate_encryption_invalid_seed() {
+        // Seed is validated before the client is looked up
+        let result = vss_rotate_encryption("not-hex".to_string()).await;
+        assert!(matches!(result, Err(VssError::InvalidData { .. })));
+
+        let result = vss_rotate_encryption("abcd".to_string()).await;
+        assert!(matches!(result, Err(VssError::InvalidData { .. })));
+    }
+    
+    #[tokio::test]
+    async fn test_ffi_rotate_encryption_keeps_replaced_client() {
+        let _guard = GLOBAL_CLIENT.lock().await;
+        let client = VssClient::new_in_memory_with_seed(TEST_STORE_ID.to_string(), [7u8; 32]).unwrap();
+        install_client(client.clone());
+        vss_store("k".to_string(), vec![1]).await.unwrap();
+        assert_eq!(vss_rotate_encryption("08".repeat(32)).await.unwrap(), 1);
+        assert_eq!(vss_get("k".to_string()).await.unwrap().unwrap().value, vec![1]);
+
+        // A client installed while an operation ran is not overwritten by its result
+        let (_, generation) = try_get_client_with_generation().unwrap();
+        let replacement = VssClient::new_in_memory(TEST_STORE_ID.to_string());
+        let replaced_generation = install_client(replacement);
+        assert!(matches!(
+            install_client_if_current(client, generation),
+            Err(VssError::ConflictError { .. })
+        ));
+        assert_eq!(vss_client_generation(), replaced_generation);
+        assert!(!try_get_client().unwrap().is_encrypted());
+        vss_shutdown_client();
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_ffi_replace_client_during_operations() {
+        let _guard = GLOBAL_CLIENT.lock().await;
+        install_client(VssClient::new_in_memory(TEST_STORE_ID.to_string()));
+        let start_generation = vss_client_generation();
+
+        let mut workers = tokio::task::JoinSet::new();
+        for worker in 0..8 {
+            workers.spawn(async move {
+                for i in 0..50 {
+                    let key = format!("worker-{}-{}", worker, i);
+                    vss_store(key.clone(), vec![i as u8]).await?;
+                    vss_get(key).await?;
+                }
+                Ok::<(), VssError>(())
+            });
+        }
+
+        for _ in 0..20 {
+            install_client(VssClient::new_in_memory(TEST_STORE_ID.to_string()));
+            tokio::task::yield_now().await;
+        }
+
+        // No operation may observe a missing client while clients are swapped
+        while let Some(joined) = workers.join_next().await {
+            joined.unwrap().expect("operation failed during client replacement");
+        }
+        assert_eq!(vss_client_generation(), start_generation + 20);
+
+        let result = vss_replace_client(
+            MOCK_BASE_URL.to_string(),
+            TEST_STORE_ID.to_string(),
+            VssClientConfig::default(),
+        ).await;
+        assert_eq!(result.unwrap(), start_generation + 21);
+        vss_shutdown_client();
+    }
+
+    #[tokio::test]
+    async fn test_ffi_shutdown_during_rotation() {
+        let _guard = GLOBAL_CLIENT.lock().await;
+        install_client(VssClient::new_in_memory(TEST_STORE_ID.to_string()));
+
+        // What vss_rotate_encryption does around the rotation itself
+        let (client, generation) = try_get_client_with_generation().unwrap();
+        vss_shutdown_client();
+        assert!(matches!(
+            install_client_if_current(client, generation),
+            Err(VssError::ConflictError { .. })
+        ));
+        assert!(!vss_is_initialized());
+    }
+
+    #[tokio::test]
+    async fn test_ffi_replace_client_variants() {
+        let _guard = GLOBAL_CLIENT.lock().await;
+        let generation = install_client(VssClient::new_in_memory(TEST_STORE_ID.to_string()));
+        let mnemonic = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+        let replaced = vss_replace_client_with_lnurl_auth(
+            MOCK_BASE_URL.to_string(),
+            TEST_STORE_ID.to_string(),
+            mnemonic.to_string(),
+            None,
+            "https://auth.example.com/lnurl".to_string(),
+            VssClientConfig::default(),
+        ).await.unwrap();
+        assert_eq!(replaced, generation + 1);
+        assert!(vss_is_encrypted().unwrap());
+
+        let replaced = vss_replace_client_with_lnurl_auth_seed(
+            MOCK_BASE_URL.to_string(),
+            TEST_STORE_ID.to_string(),
+            vec![7; 32],
+            "https://auth.example.com/lnurl".to_string(),
+            VssClientConfig::default(),
+        ).await.unwrap();
+        assert_eq!(replaced, generation + 2);
+
+        let pins = vec![CertificatePin::SpkiSha256 { hash: vec![0; 32] }];
+        let replaced = vss_replace_client_with_pinned_certificates(
+            MOCK_BASE_URL.to_string(),
+            TEST_STORE_ID.to_string(),
+            pins.clone(),
+            VssClientConfig::default(),
+        ).await.unwrap();
+        assert_eq!(replaced, generation + 3);
+        assert!(!vss_is_encrypted().unwrap());
+
+        // A client that can't be built leaves the current one in place
+        assert!(vss_replace_client_with_lnurl_auth_seed(
+            MOCK_BASE_URL.to_string(),
+            TEST_STORE_ID.to_string(),
+            vec![7; 5],
+            "https://auth.example.com/lnurl".to_string(),
+            VssClientConfig::default(),
+        ).await.is_err());
+        assert!(vss_replace_client_with_pinned_certificates(
+            "http://vss.example.com".to_string(),
+            TEST_STORE_ID.to_string(),
+            pins,
+            VssClientConfig::default(),
+        ).await.is_err());
+        assert_eq!(vss_client_generation(), generation + 3);
+        assert!(vss_is_initialized());
+        vss_shutdown_client();
+    }
+    
+    #[tokio::test]
+    async fn test_ffi_key_cursor() {
+        let _guard = GLOBAL_CLIENT.lock().await;
+        install_client(VssClient::new_in_memory(TEST_STORE_ID.to_string()));
+        for i in 0..5 {
+            vss_store(format!("a/{}", i), vec![i as u8]).await.unwrap();
+        }
+        vss_store("b/0".to_string(), vec![0]).await.unwrap();
+
+        let cursor = vss_key_cursor(Some("a/".to_string()), Some(2)).unwrap();
+        let mut keys = Vec::new();
+        loop {
+            let page = cursor.next_page().await.unwrap();
+            if page.is_empty() {
+                break;
+            }
+            assert!(page.len() <= 2);
+            keys.extend(page.into_iter().map(|kv| kv.key));
+        }
+        keys.sort();
+        assert_eq!(keys, vec!["a/0", "a/1", "a/2", "a/3", "a/4"]);
+
+        // An exhausted cursor stays exhausted
+        assert!(cursor.next_page().await.unwrap().is_empty());
+        vss_shutdown_client();
+
+        let result = vss_key_cursor(None, None);
+        assert!(matches!(result, Err(VssError::ConnectionError { .. })));
+    }
+    
+    #[tokio::test]
+    async fn test_ffi_cancel_handle() {
+        let _guard = GLOBAL_CLIENT.lock().await;
+        install_client(VssClient::new_in_memory(TEST_STORE_ID.to_string()));
+        vss_store("a".to_string(), vec![1]).await.unwrap();
+
+        let cancel = VssCancelHandle::new();
+        cancel.cancel();
+        assert!(matches!(
+            vss_get_many_partial(vec!["a".to_string()], Some(cancel.clone())).await,
+            Err(VssError::Cancelled)
+        ));
+        assert!(matches!(
+            vss_clear_store(true, false, Some(cancel.clone())).await,
+            Err(VssError::Cancelled)
+        ));
+        assert!(matches!(
+            vss_delete_prefix_older_than("".to_string(), 10, Some(cancel)).await,
+            Err(VssError::Cancelled)
+        ));
+        assert_eq!(vss_clear_store(true, false, None).await.unwrap(), vec!["a"]);
+        vss_shutdown_client();
+    }
+    
+    #[tokio::test]
+    async fn test_ffi_store_debounced() {
+        let _guard = GLOBAL_CLIENT.lock().await;
+        install_client(VssClient::new_in_memory(TEST_STORE_ID.to_string()));
+
+        vss_store_debounced("volume".to_string(), vec![1], 60_000).unwrap();
+        vss_store_debounced("volume".to_string(), vec![2], 60_000).unwrap();
+        assert!(vss_get("volume".to_string()).await.unwrap().is_none());
+
+        assert_eq!(vss_flush().await.unwrap(), 1);
+        assert_eq!(vss_get("volume".to_string()).await.unwrap().unwrap().value, vec![2]);
+        vss_shutdown_client();
+    }
+    
+    #[tokio::test]
+    async fn test_ffi_shutdown_client_graceful() {
+        let _guard = GLOBAL_CLIENT.lock().await;
+        let client = VssClient::new_in_memory(TEST_STORE_ID.to_string());
+        install_client(client.clone());
+
+        vss_store_debounced("volume".to_string(), vec![3], 60_000).unwrap();
+        vss_shutdown_client_graceful(5_000).await.unwrap();
+        assert!(!vss_is_initialized());
+        assert_eq!(client.get("volume".to_string()).await.unwrap().unwrap().value, vec![3]);
+
+        // An operation that doesn't finish in time
+        install_client(client);
+        let running = InFlightGuard::enter();
+        assert!(matches!(
+            vss_shutdown_client_graceful(50).await,
+            Err(VssError::TimeoutError { .. })
+        ));
+        assert!(!vss_is_initialized());
+        drop(running);
+
+        assert!(vss_shutdown_client_graceful(1_000).await.is_ok());
+
+        // The client is cleared before waiting, so new operations don't extend the wait
+        install_client(VssClient::new_in_memory(TEST_STORE_ID.to_string()));
+        let running = InFlightGuard::enter();
+        let shutdown = tokio::spawn(vss_shutdown_client_graceful(5_000));
+        while vss_is_initialized() {
+            tokio::task::yield_now().await;
+        }
+        assert!(matches!(vss_get("volume".to_string()).await, Err(VssError::ConnectionError { .. })));
+        assert!(!shutdown.is_finished());
+        drop(running);
+        assert!(shutdown.await.unwrap().is_ok());
+    }
+
+    #[test]
+    fn test_ffi_init_runtime() {
+        assert!(matches!(vss_init_runtime(0), Err(VssError::InvalidData { .. })));
+
+        // Only the first initialization succeeds
+        assert!(vss_init_runtime(2).is_ok());
+        assert!(vss_init_runtime(2).is_err());
+    }
+    
     /*
     // Integration tests for FFI functions would go here
     // These require a live VSS server - see tests.rs for setup instructions
@@ -1,39 +1,157 @@
-use super::errors::VssError;
+use super::errors::{display_key, VssError};
+#[cfg(any(test, feature = "test-utils"))]
+use super::in_memory::InMemoryStore;
 use super::types::*;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
 use bitcoin::bip32::{ChildNumber, Xpriv};
 use bitcoin::hashes::{sha256, Hash, HashEngine, Hmac, HmacEngine};
-use bitcoin::secp256k1::Secp256k1;
+use bitcoin::hex::DisplayHex;
+use bitcoin::secp256k1::{All, Secp256k1};
 use bitcoin::Network;
 use prost::Message;
 use rand::RngCore;
-use std::collections::HashMap;
-use std::sync::Arc;
+use reqwest::header::{HeaderValue, USER_AGENT};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
 use vss_client::client::VssClient as ExternalVssClient;
 use vss_client::error::VssError as ExternalVssError;
-use vss_client::headers::{FixedHeaders, LnurlAuthToJwtProvider, VssHeaderProvider};
+use vss_client::headers::{
+    FixedHeaders, LnurlAuthToJwtProvider, VssHeaderProvider, VssHeaderProviderError,
+};
 use vss_client::types::{
-    DeleteObjectRequest, GetObjectRequest, KeyValue as ExternalKeyValue, ListKeyVersionsRequest,
-    PutObjectRequest, Storable,
+    DeleteObjectRequest, DeleteObjectResponse, GetObjectRequest, GetObjectResponse,
+    KeyValue as ExternalKeyValue, ListKeyVersionsRequest,
+    ListKeyVersionsResponse as ExternalListKeyVersionsResponse, PutObjectRequest,
+    PutObjectResponse, Storable,
 };
 use vss_client::util::key_obfuscator::KeyObfuscator;
 use vss_client::util::retry::{
     ExponentialBackoffRetryPolicy, FilteredRetryPolicy, JitteredRetryPolicy,
-    MaxAttemptsRetryPolicy, MaxTotalDelayRetryPolicy, RetryPolicy,
+    MaxAttemptsRetryPolicy, MaxTotalDelayRetryPolicy, RetryContext, RetryPolicy,
 };
 use vss_client::util::storable_builder::{EntropySource, StorableBuilder};
 use bip39::Mnemonic;
+use once_cell::sync::Lazy;
 use std::str::FromStr;
 
-const VSS_HARDENED_CHILD_INDEX: u32 = 877;
-const VSS_LNURL_AUTH_HARDENED_CHILD_INDEX: u32 = 138;
-const VSS_STORE_ID_HARDENED_CHILD_INDEX: u32 = 118;
+/// Hardened child index of the VSS root key, `m/877'`. Encryption and obfuscation keys
+/// are derived from this key's private key.
+pub const VSS_HARDENED_CHILD_INDEX: u32 = 877;
+/// Hardened child index of the LNURL-auth key under the VSS root, `m/877'/138'`.
+pub const VSS_LNURL_AUTH_HARDENED_CHILD_INDEX: u32 = 138;
+/// Hardened child index of the store ID key under the VSS root, `m/877'/118'`.
+pub const VSS_STORE_ID_HARDENED_CHILD_INDEX: u32 = 118;
 const VSS_STORE_ID_HASH_LENGTH: usize = 36;
 
+/// The manifest `store_large` writes at a chunked value's own key
+#[derive(Serialize, Deserialize)]
+struct LargeValueManifest {
+    /// Path segment the chunks of this write are stored under. Manifests written before
+    /// chunks were stored per write have none.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    generation: Option<String>,
+    chunks: u32,
+    total_len: u64,
+    /// Hex SHA-256 of the whole value, catching chunks left over from another write
+    sha256: String,
+}
+
+/// Signing context shared by all key derivations. Creating one precomputes large tables,
+/// so it is built once instead of on every derivation.
+static SECP: Lazy<Secp256k1<All>> = Lazy::new(Secp256k1::new);
+
+/// Attempts `update` makes before giving up on a key that keeps changing under it.
+pub(crate) const MAX_UPDATE_ATTEMPTS: u32 = 5;
+
+/// Maximum number of gets in flight when fetching the values of a page of keys.
+const MAX_CONCURRENT_GETS: usize = 16;
+
+/// Shared keys whose values `diff_against` holds in memory at once, per store.
+const DIFF_BATCH_KEYS: usize = 256;
+
+/// Reads made by `get_consistent` before it settles for what the server returns.
+const CONSISTENT_READ_ATTEMPTS: u32 = 5;
+
+/// Base delay between consistent reads, multiplied by the number of reads so far.
+const CONSISTENT_READ_DELAY: Duration = Duration::from_millis(50);
+
+/// Keys whose last write `get_consistent` remembers. Beyond this the least recently
+/// written keys are forgotten and read once, like `get`.
+const MAX_TRACKED_WRITES: usize = 4096;
+
+/// Values `get_cached` keeps. Beyond this the least recently used ones are dropped.
+const MAX_CACHED_ITEMS: usize = 1024;
+
+/// First delay between the version checks of `wait_for_version`, doubled after each check.
+const WAIT_POLL_DELAY: Duration = Duration::from_millis(50);
+
+/// Longest delay between the version checks of `wait_for_version`.
+const WAIT_POLL_MAX_DELAY: Duration = Duration::from_secs(2);
+
+/// How long reqwest keeps an idle connection open. Within this window of a response the
+/// next request likely reuses a pooled connection, so the connect timeout isn't checked.
+const POOLED_CONNECTION_IDLE: Duration = Duration::from_secs(90);
+
+/// Environment variables through which reqwest picks up a proxy.
+const PROXY_ENV_VARS: [&str; 6] =
+    ["ALL_PROXY", "all_proxy", "HTTPS_PROXY", "https_proxy", "HTTP_PROXY", "http_proxy"];
+
+/// Size in bytes of the chunks `store_large` splits values into.
+pub const LARGE_VALUE_CHUNK_BYTES: usize = 512 * 1024;
+
+/// Content type of the manifest `store_large` writes at a chunked value's own key.
+pub const LARGE_VALUE_MANIFEST_CONTENT_TYPE: &str = "application/vnd.vss.chunked+json";
+
+/// Keys requested per page by operations that page through a listing internally, unless
+/// `default_page_size` is set.
+pub const DEFAULT_PAGE_SIZE: u32 = 1000;
+
+/// Sample key `verify_key_obfuscation` round-trips through the key obfuscator.
+const OBFUSCATION_CHECK_KEY: &str = "vss-key-obfuscation-check";
+
+/// Marks a key stored under the SHA-256 hash of the original key.
+pub const HASHED_KEY_PREFIX: &str = "sha256:";
+
+/// Key prefix reserved for keys managed by this crate. User writes may not use it.
+pub const RESERVED_KEY_PREFIX: &str = "__vss__/";
+
+/// Cipher format of the `Storable` envelopes written by `StorableBuilder`, the only one
+/// this crate can read.
+const SUPPORTED_CIPHER_FORMAT: &str = "ChaCha20Poly1305";
+
+/// Start of every header this crate puts in front of a decrypted value. User values that
+/// start with it are stored behind `ESCAPE_MAGIC`, so they are never read as a header.
+const RESERVED_VALUE_PREFIX: &[u8] = b"\x00vss-";
+
+/// Marks a decrypted value that starts with a content type header: the magic, a length
+/// byte and the UTF-8 content type, followed by the value itself.
+const CONTENT_TYPE_MAGIC: &[u8] = b"\x00vss-ct\x01";
+
+/// Marks a decrypted value whose remainder is the user value, taken as is. Written instead
+/// of a content type header for values starting with `RESERVED_VALUE_PREFIX`.
+const ESCAPE_MAGIC: &[u8] = b"\x00vss-raw\x01";
+
+/// Marks a decrypted value that starts with an integrity header: the magic and the SHA-256
+/// of the rest of the plaintext, which may itself start with a content type header.
+const CHECKSUM_MAGIC: &[u8] = b"\x00vss-ck\x01";
+
+/// Store ID layout used by `derive_vss_store_id`: `{prefix}` and `{id}` are replaced with
+/// the prefix and the derived identifier.
+pub const DEFAULT_STORE_ID_FORMAT: &str = "{prefix}_{id}";
+
 /// Derives a deterministic VSS store ID from a mnemonic and optional passphrase.
 ///
 /// # Parameters
 /// - `prefix`: A prefix to include in the store ID
-/// - `mnemonic`: BIP39 mnemonic phrase (12 or 24 words)
+/// - `mnemonic`: BIP39 mnemonic phrase (12, 15, 18, 21 or 24 words)
 /// - `passphrase`: Optional BIP39 passphrase
 ///
 /// # Returns
@@ -43,21 +161,58 @@ pub fn derive_vss_store_id(
     mnemonic: String,
     passphrase: Option<String>,
 ) -> Result<String, VssError> {
-    let mnemonic = Mnemonic::from_str(&mnemonic).map_err(|e| VssError::ConnectionError {
-        error_details: format!("Invalid mnemonic: {}", e),
-    })?;
+    Ok(derive_vss_store_id_parts(prefix, mnemonic, passphrase)?.full)
+}
+
+/// Derives a VSS store ID like `derive_vss_store_id`, laid out by a template.
+///
+/// # Parameters
+/// - `prefix`: A prefix to include in the store ID
+/// - `mnemonic`: BIP39 mnemonic phrase (12, 15, 18, 21 or 24 words)
+/// - `passphrase`: Optional BIP39 passphrase
+/// - `format`: Template in which `{prefix}` and `{id}` are replaced with the prefix and the
+///   derived identifier. Must contain `{id}`
+///
+/// # Returns
+/// A store ID string or VssError on failure
+pub fn derive_vss_store_id_with_format(
+    prefix: String,
+    mnemonic: String,
+    passphrase: Option<String>,
+    format: String,
+) -> Result<String, VssError> {
+    if !format.contains("{id}") {
+        return Err(VssError::InvalidData {
+            error_details: format!("Store ID format '{}' must contain {{id}}", format),
+        });
+    }
+
+    let parts = derive_vss_store_id_parts(prefix, mnemonic, passphrase)?;
+    Ok(format_store_id(&format, &parts.prefix, &parts.derived_id))
+}
+
+/// Derives a VSS store ID like `derive_vss_store_id`, returning its components.
+///
+/// # Parameters
+/// - `prefix`: A prefix to include in the store ID
+/// - `mnemonic`: BIP39 mnemonic phrase (12, 15, 18, 21 or 24 words)
+/// - `passphrase`: Optional BIP39 passphrase
+///
+/// # Returns
+/// The prefix, the derived identifier and the full store ID, or VssError on failure
+pub fn derive_vss_store_id_parts(
+    prefix: String,
+    mnemonic: String,
+    passphrase: Option<String>,
+) -> Result<StoreIdParts, VssError> {
+    let mnemonic = parse_mnemonic(&mnemonic)?;
 
     let seed = match passphrase {
         Some(passphrase) => mnemonic.to_seed(&passphrase),
         None => mnemonic.to_seed(""),
     };
-    let seed_array: [u8; 32] = seed[..32]
-        .try_into()
-        .map_err(|_| VssError::ConnectionError {
-            error_details: "Failed to extract seed from mnemonic".to_string(),
-        })?;
+    let seed_array = master_seed(&seed)?;
 
-    let secp = Secp256k1::new();
     let master_xprv = Xpriv::new_master(Network::Bitcoin, &seed_array).map_err(|e| {
         VssError::ConnectionError {
             error_details: format!("Failed to create master key: {}", e),
@@ -66,7 +221,7 @@ pub fn derive_vss_store_id(
 
     let vss_store_id_xprv = master_xprv
         .derive_priv(
-            &secp,
+            &SECP,
             &[
                 ChildNumber::Hardened { index: VSS_HARDENED_CHILD_INDEX },
                 ChildNumber::Hardened { index: VSS_STORE_ID_HARDENED_CHILD_INDEX },
@@ -80,416 +235,4555 @@ pub fn derive_vss_store_id(
     let hash = sha256::Hash::hash(&store_id_key);
     let hash_hex = hash.to_string();
 
-    let store_id_suffix = &hash_hex[..VSS_STORE_ID_HASH_LENGTH];
-    let store_id = format!("{}_{}", prefix, store_id_suffix);
+    let derived_id = hash_hex[..VSS_STORE_ID_HASH_LENGTH].to_string();
+    let full = format_store_id(DEFAULT_STORE_ID_FORMAT, &prefix, &derived_id);
+
+    Ok(StoreIdParts { prefix, derived_id, full })
+}
+
+/// Fills the `{prefix}` and `{id}` placeholders of a store ID template in a single pass, so
+/// placeholders within the substituted values are left as they are
+fn format_store_id(format: &str, prefix: &str, derived_id: &str) -> String {
+    let mut formatted = String::with_capacity(format.len() + prefix.len() + derived_id.len());
+    let mut rest = format;
+    while let Some(start) = rest.find('{') {
+        formatted.push_str(&rest[..start]);
+        rest = &rest[start..];
+        if let Some(after) = rest.strip_prefix("{prefix}") {
+            formatted.push_str(prefix);
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("{id}") {
+            formatted.push_str(derived_id);
+            rest = after;
+        } else {
+            formatted.push('{');
+            rest = &rest[1..];
+        }
+    }
+    formatted.push_str(rest);
+    formatted
+}
+
+/// Reduces a 32- or 64-byte seed to the 32-byte master seed used for key derivation.
+///
+/// A 64-byte BIP39 seed is truncated to its first 32 bytes, matching what the mnemonic
+/// based functions derive from the same phrase.
+///
+/// # Parameters
+/// - `seed`: The seed bytes (32 or 64 bytes)
+///
+/// # Returns
+/// The 32-byte master seed or VssError::InvalidData for any other length
+pub(crate) fn master_seed(seed: &[u8]) -> Result<[u8; 32], VssError> {
+    match seed.len() {
+        32 | 64 => Ok(seed[..32].try_into().expect("slice is 32 bytes")),
+        len => Err(VssError::InvalidData {
+            error_details: format!("Seed must be 32 or 64 bytes, got {}", len),
+        }),
+    }
+}
+
+/// Parses a BIP39 mnemonic after normalizing whitespace and case.
+///
+/// Words may be separated by any run of whitespace and written in any case. Failures are
+/// reported as InvalidData, distinguishing a wrong word count, an invalid word and a bad
+/// checksum. Invalid words are identified by position only, to keep the phrase out of logs.
+///
+/// # Parameters
+/// - `mnemonic`: BIP39 mnemonic phrase (12, 15, 18, 21 or 24 words)
+///
+/// # Returns
+/// The parsed Mnemonic or VssError::InvalidData
+pub(crate) fn parse_mnemonic(mnemonic: &str) -> Result<Mnemonic, VssError> {
+    let normalized = mnemonic
+        .split_whitespace()
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    Mnemonic::from_str(&normalized).map_err(|e| {
+        let reason = match e {
+            bip39::Error::BadWordCount(count) => format!(
+                "wrong word count: got {}, expected 12, 15, 18, 21 or 24",
+                count
+            ),
+            bip39::Error::UnknownWord(index) => {
+                format!("invalid word at position {}", index + 1)
+            }
+            bip39::Error::InvalidChecksum => "bad checksum".to_string(),
+            other => other.to_string(),
+        };
+        VssError::InvalidData {
+            error_details: format!("Invalid mnemonic: {}", reason),
+        }
+    })
+}
+
+/// Returns the BIP32 paths this crate derives its keys at, for audits and interop.
+pub fn derivation_info() -> DerivationInfo {
+    let vss_root_path = format!("m/{}'", VSS_HARDENED_CHILD_INDEX);
+    DerivationInfo {
+        lnurl_auth_path: format!("{}/{}'", vss_root_path, VSS_LNURL_AUTH_HARDENED_CHILD_INDEX),
+        store_id_path: format!("{}/{}'", vss_root_path, VSS_STORE_ID_HARDENED_CHILD_INDEX),
+        vss_root_path,
+    }
+}
+
+/// Derives the keys used to encrypt values and obfuscate keys from a 32-byte master seed.
+///
+/// The seed is first derived to the VSS root key at `m/877'`, whose private key then goes
+/// through the HKDF steps in `derive_data_encryption_and_obfuscation_keys`. These are the
+/// exact keys used by clients created with LNURL-auth from the same seed and the default
+/// HKDF salts.
+///
+/// # Parameters
+/// - `seed`: The seed bytes for key derivation (32 bytes)
+///
+/// # Returns
+/// The hex-encoded data encryption and obfuscation keys or VssError on failure
+pub fn derive_storage_keys(seed: [u8; 32]) -> Result<StorageKeys, VssError> {
+    let vss_xprv = derive_vss_xprv(&SECP, &seed)?;
+    let (data_encryption_key, obfuscation_key) =
+        derive_data_encryption_and_obfuscation_keys(
+            &vss_xprv.private_key.secret_bytes(),
+            &VssClientConfig::default(),
+        );
+
+    Ok(StorageKeys {
+        data_encryption_key: data_encryption_key.to_lower_hex_string(),
+        obfuscation_key: obfuscation_key.to_lower_hex_string(),
+    })
+}
+
+/// Decrypts a stored value obtained outside a client, e.g. from a server database dump.
+///
+/// Uses the data encryption key `derive_storage_keys` derives from the seed, so it reads
+/// values written by LNURL-auth clients created from the same seed with the default HKDF
+/// salts. Any content type or integrity header is removed, and the checksum verified.
+/// Errors name the value `blob`.
+///
+/// # Parameters
+/// - `seed`: The seed bytes for key derivation (32 bytes)
+/// - `blob`: The serialized `Storable` as held by the server
+///
+/// # Returns
+/// The decrypted value or VssError on failure
+pub fn decrypt_blob(seed: [u8; 32], blob: &[u8]) -> Result<Vec<u8>, VssError> {
+    const BLOB_KEY: &str = "blob";
+
+    let vss_xprv = derive_vss_xprv(&SECP, &seed)?;
+    let (data_encryption_key, _) = derive_data_encryption_and_obfuscation_keys(
+        &vss_xprv.private_key.secret_bytes(),
+        &VssClientConfig::default(),
+    );
+    let builder = StorableBuilder::new(data_encryption_key, ClientEntropySource::Rand);
 
-    Ok(store_id)
+    let plaintext = decrypt_storable(&builder, true, BLOB_KEY, blob)?;
+    let (value, _) = split_content_type(verify_checksum(BLOB_KEY, plaintext)?);
+    Ok(value)
 }
 
-type CustomRetryPolicy = FilteredRetryPolicy<
-    JitteredRetryPolicy<
-        MaxTotalDelayRetryPolicy<
-            MaxAttemptsRetryPolicy<ExponentialBackoffRetryPolicy<ExternalVssError>>,
+type CustomRetryPolicy = TrackedRetryPolicy<
+    FilteredRetryPolicy<
+        JitteredRetryPolicy<
+            MaxTotalDelayRetryPolicy<
+                MaxAttemptsRetryPolicy<ExponentialBackoffRetryPolicy<ExternalVssError>>,
+            >,
         >,
+        Box<dyn Fn(&ExternalVssError) -> bool + 'static + Send + Sync>,
     >,
-    Box<dyn Fn(&ExternalVssError) -> bool + 'static + Send + Sync>,
 >;
 
-/// A source for generating entropy/randomness using [`rand`].
-pub(crate) struct RandEntropySource;
+tokio::task_local! {
+    /// The request currently running on this task.
+    static CURRENT_REQUEST: RequestState;
+}
 
-impl EntropySource for RandEntropySource {
-    fn fill_bytes(&self, buffer: &mut [u8]) {
-        rand::thread_rng().fill_bytes(buffer);
-    }
+/// The endpoint a request is sent to and how many of its attempts failed so far.
+struct RequestState {
+    operation: &'static str,
+    failed_attempts: Cell<u32>,
 }
 
-#[derive(Clone)]
-pub struct VssClient {
-    inner: Arc<ExternalVssClient<CustomRetryPolicy>>,
-    store_id: String,
-    storable_builder: Arc<StorableBuilder<RandEntropySource>>,
-    key_obfuscator: Option<Arc<KeyObfuscator>>,
+/// The retry listener of a client, shared between the client and its retry policy so it can
+/// be set after the policy was handed to `vss-client`.
+pub(crate) type RetryListenerSlot = Arc<RwLock<Option<Arc<dyn VssRetryListener>>>>;
+
+thread_local! {
+    /// Set by the retry filter when the error being considered is a rate limit. The filter
+    /// and `TrackedRetryPolicy::next_delay` run in the same synchronous call.
+    static RATE_LIMITED: Cell<bool> = const { Cell::new(false) };
 }
 
-impl VssClient {
-    /// Creates a new VSS client instance.
-    ///
-    /// # Parameters
-    /// - `base_url`: The VSS server URL
-    /// - `store_id`: The storage namespace identifier
-    ///
-    /// # Returns
-    /// A new VssClient instance or VssError on failure
-    pub async fn new(base_url: String, store_id: String) -> Result<Self, VssError> {
-        let header_provider = Arc::new(FixedHeaders::new(HashMap::new()));
+/// Minimum wait before retrying a rate-limited request. `vss-client` doesn't expose
+/// response headers, so the server's `Retry-After` can't be read.
+const RATE_LIMIT_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Counts failed attempts of a request while deferring every decision to `inner`, except
+/// that retries after a rate limit wait at least `RATE_LIMIT_RETRY_DELAY`. Each retry is
+/// reported to the listener in `listener`, if one is set.
+///
+/// `vss-client` keeps its `RetryContext` fields private, so the count is taken from the
+/// number of times the policy is consulted, which happens exactly once per failed attempt.
+pub(crate) struct TrackedRetryPolicy<P> {
+    inner: P,
+    listener: RetryListenerSlot,
+}
 
-        Self::new_with_header_provider(base_url, store_id, header_provider, None).await
+impl<P> TrackedRetryPolicy<P> {
+    pub(crate) fn new(inner: P) -> Self {
+        Self { inner, listener: RetryListenerSlot::default() }
     }
 
-    /// Creates a new VSS client instance with LNURL-auth.
-    ///
-    /// # Parameters
-    /// - `base_url`: The VSS server URL
-    /// - `store_id`: The storage namespace identifier
-    /// - `seed`: The seed bytes for key derivation (32 bytes)
-    /// - `lnurl_auth_server_url`: The LNURL-auth server URL
-    ///
-    /// # Returns
-    /// A new VssClient instance or VssError on failure
-    pub async fn new_with_lnurl_auth(
-        base_url: String,
-        store_id: String,
-        seed: [u8; 32],
-        lnurl_auth_server_url: String,
-    ) -> Result<Self, VssError> {
-        let secp = Secp256k1::new();
-        let master_xprv =
-            Xpriv::new_master(Network::Bitcoin, &seed).map_err(|e| VssError::ConnectionError {
-                error_details: format!("Failed to create master key: {}", e),
-            })?;
+    /// Returns the slot through which the retry listener can be set
+    pub(crate) fn listener_slot(&self) -> RetryListenerSlot {
+        self.listener.clone()
+    }
+}
 
-        let vss_xprv = master_xprv
-            .derive_priv(
-                &secp,
-                &[ChildNumber::Hardened {
-                    index: VSS_HARDENED_CHILD_INDEX,
-                }],
-            )
-            .map_err(|e| VssError::ConnectionError {
-                error_details: format!("Failed to derive VSS key: {}", e),
-            })?;
+impl<P: RetryPolicy<E = ExternalVssError>> RetryPolicy for TrackedRetryPolicy<P> {
+    type E = ExternalVssError;
 
-        let lnurl_auth_xprv = vss_xprv
-            .derive_priv(
-                &secp,
-                &[ChildNumber::Hardened {
-                    index: VSS_LNURL_AUTH_HARDENED_CHILD_INDEX,
-                }],
-            )
-            .map_err(|e| VssError::ConnectionError {
-                error_details: format!("Failed to derive LNURL-auth key: {}", e),
-            })?;
+    fn next_delay(&self, context: &RetryContext<Self::E>) -> Option<Duration> {
+        let request = CURRENT_REQUEST
+            .try_with(|request| {
+                request.failed_attempts.set(request.failed_attempts.get() + 1);
+                (request.operation, request.failed_attempts.get())
+            })
+            .ok();
+        RATE_LIMITED.with(|limited| limited.set(false));
+        let mut delay = self.inner.next_delay(context)?;
+        if RATE_LIMITED.with(Cell::get) {
+            delay = delay.max(RATE_LIMIT_RETRY_DELAY);
+        }
 
-        let lnurl_auth_jwt_provider =
-            LnurlAuthToJwtProvider::new(lnurl_auth_xprv, lnurl_auth_server_url, HashMap::new())
-                .map_err(|e| VssError::ConnectionError {
-                    error_details: format!("Failed to create LNURL-auth provider: {}", e),
-                })?;
+        // Only read on a retry, so requests that succeed never touch the slot
+        let listener = self.listener.read().unwrap().clone();
+        if let (Some(listener), Some((operation, failed_attempts))) = (listener, request) {
+            listener.on_retry(operation.to_string(), failed_attempts + 1, delay.as_millis() as u64);
+        }
+        Some(delay)
+    }
+}
 
-        let header_provider = Arc::new(lnurl_auth_jwt_provider);
+/// A failed backend request along with how much effort went into it.
+#[derive(Debug)]
+pub(crate) struct BackendError {
+    pub(crate) error: ExternalVssError,
+    /// Attempts made, including retries, when the request went over the network
+    pub(crate) attempts: Option<u32>,
+    /// Time spent on the request across all attempts and backoff delays
+    pub(crate) elapsed_ms: Option<u64>,
+    /// Set when the request was abandoned for exceeding one of the client's timeouts
+    pub(crate) timed_out: Option<TimedOut>,
+}
 
-        let vss_seed_bytes: [u8; 32] = vss_xprv.private_key.secret_bytes();
+impl From<ExternalVssError> for BackendError {
+    fn from(error: ExternalVssError) -> Self {
+        Self { error, attempts: None, elapsed_ms: None, timed_out: None }
+    }
+}
 
-        Self::new_with_header_provider(base_url, store_id, header_provider, Some(vss_seed_bytes))
-            .await
+impl BackendError {
+    fn timed_out(timed_out: TimedOut) -> Self {
+        let message = match &timed_out {
+            TimedOut::Connect(message) | TimedOut::Read(message) => message.clone(),
+        };
+        Self { timed_out: Some(timed_out), ..ExternalVssError::InternalServerError(message).into() }
     }
+}
 
-    /// Internal method to create a client with any header provider
-    async fn new_with_header_provider(
-        base_url: String,
-        store_id: String,
-        header_provider: Arc<dyn VssHeaderProvider>,
-        vss_seed: Option<[u8; 32]>,
-    ) -> Result<Self, VssError> {
-        let retry_policy = ExponentialBackoffRetryPolicy::new(std::time::Duration::from_millis(10))
-            .with_max_attempts(10)
-            .with_max_total_delay(std::time::Duration::from_secs(15))
-            .with_max_jitter(std::time::Duration::from_millis(10))
-            .skip_retry_on_error(Box::new(|e: &ExternalVssError| {
-                matches!(
-                    e,
-                    ExternalVssError::NoSuchKeyError(..)
-                        | ExternalVssError::InvalidRequestError(..)
-                        | ExternalVssError::ConflictError(..)
-                )
-            }) as _);
+/// Which phase of a request ran out of time, with a description of what happened.
+#[derive(Debug)]
+pub(crate) enum TimedOut {
+    /// The server could not be connected to within `connect_timeout_ms`
+    Connect(String),
+    /// The server did not respond within `read_timeout_ms`
+    Read(String),
+}
+
+/// The time limits applied to the requests of a server-backed client.
+///
+/// Clients built on their own HTTP client set reqwest's connect and request timeouts on
+/// it. `vss-client` builds its own HTTP client when given a header provider though, so for
+/// those the connection is checked with a separate bounded TCP connect instead. Either way
+/// the whole request, retries included, is bounded from the outside.
+pub(crate) struct RequestTimeouts {
+    connect: Option<ConnectProbe>,
+    read: Option<Duration>,
+}
 
-        let client = ExternalVssClient::new_with_headers(base_url, retry_policy, header_provider);
+/// Checks that the server accepts TCP connections before a request that may need a new one.
+struct ConnectProbe {
+    host: String,
+    port: u16,
+    timeout: Duration,
+    /// When the server last accepted a connection or answered a request
+    reachable_at: Mutex<Option<Instant>>,
+}
 
-        let (storable_builder, key_obfuscator) = if let Some(seed) = vss_seed {
-            let (data_encryption_key, obfuscation_master_key) =
-                derive_data_encryption_and_obfuscation_keys(&seed);
-            let builder = Arc::new(StorableBuilder::new(data_encryption_key, RandEntropySource));
-            let obfuscator = Some(Arc::new(KeyObfuscator::new(obfuscation_master_key)));
-            (builder, obfuscator)
-        } else {
-            let zero_key = [0u8; 32];
-            let builder = Arc::new(StorableBuilder::new(zero_key, RandEntropySource));
-            (builder, None)
-        };
+impl RequestTimeouts {
+    /// Takes the timeouts from `config`. The connect check is only made with `probe_connect`,
+    /// for clients whose HTTP client can't enforce the connect timeout itself. It is skipped
+    /// when a proxy is set in `config` or the environment, since requests then connect to the
+    /// proxy instead of the server.
+    pub(crate) fn new(base_url: &str, config: &VssClientConfig, probe_connect: bool) -> Self {
+        let proxied = config.proxy_url.is_some()
+            || PROXY_ENV_VARS.iter().any(|var| std::env::var_os(var).is_some());
+        let connect = config
+            .connect_timeout_ms
+            .filter(|_| probe_connect && !proxied)
+            .and_then(|timeout_ms| {
+                let url = Url::parse(base_url).ok()?;
+                // IPv6 hosts are bracketed in URLs but not in socket addresses
+                let host = url.host_str()?.trim_start_matches('[').trim_end_matches(']');
+                Some(ConnectProbe {
+                    host: host.to_string(),
+                    port: url.port_or_known_default()?,
+                    timeout: Duration::from_millis(timeout_ms),
+                    reachable_at: Mutex::new(None),
+                })
+            });
 
-        Ok(VssClient {
-            inner: Arc::new(client),
-            store_id,
-            storable_builder,
-            key_obfuscator,
-        })
+        Self { connect, read: config.read_timeout_ms.map(Duration::from_millis) }
     }
 
-    /// Stores a key-value pair. Server manages versioning automatically.
-    ///
-    /// # Parameters
-    /// - `key`: The unique key identifier
-    /// - `value`: The binary data to store
-    ///
-    /// # Returns
-    /// VssItem with the stored data and assigned version
-    pub async fn store(&self, key: String, value: Vec<u8>) -> Result<VssItem, VssError> {
-        let version = -1;
-        let storable = self.storable_builder.build(value.clone(), version);
-        let encrypted_value = storable.encode_to_vec();
+    /// Runs a request within the configured timeouts
+    async fn run<T>(
+        &self,
+        request: impl Future<Output = Result<T, BackendError>>,
+    ) -> Result<T, BackendError> {
+        if let Some(probe) = &self.connect {
+            probe.check().await?;
+        }
 
-        let request = PutObjectRequest {
-            store_id: self.store_id.clone(),
-            global_version: None,
-            transaction_items: vec![ExternalKeyValue {
-                key: self.build_key(&key),
-                version,
-                value: encrypted_value,
-            }],
-            delete_items: vec![],
+        let result = match self.read {
+            Some(read) => tokio::time::timeout(read, request).await.map_err(|_| {
+                BackendError::timed_out(TimedOut::Read(format!(
+                    "No response within {} ms",
+                    read.as_millis()
+                )))
+            })?,
+            None => request.await,
         };
-
-        match self.inner.put_object(&request).await {
-            Ok(_response) => {
-                Ok(VssItem {
-                    key: key.clone(),
-                    value,
-                    version: -1,
-                })
-            }
-            Err(e) => Err(convert_error(e, "store")),
+        if let (Some(probe), Ok(_)) = (&self.connect, &result) {
+            probe.mark_reachable();
         }
+        result
     }
+}
 
-    /// Retrieves a value by key.
-    ///
-    /// # Parameters
-    /// - `key`: The key to retrieve
-    ///
-    /// # Returns
-    /// Some(VssItem) if found, None if key doesn't exist
-    pub async fn get(&self, key: String) -> Result<Option<VssItem>, VssError> {
-        let request = GetObjectRequest {
-            store_id: self.store_id.clone(),
-            key: self.build_key(&key),
+impl ConnectProbe {
+    async fn check(&self) -> Result<(), BackendError> {
+        let reachable_at = *self.reachable_at.lock().unwrap();
+        if reachable_at.is_some_and(|at| at.elapsed() < POOLED_CONNECTION_IDLE) {
+            return Ok(());
+        }
+
+        let connect = tokio::net::TcpStream::connect((self.host.as_str(), self.port));
+        let message = match tokio::time::timeout(self.timeout, connect).await {
+            Ok(Ok(_)) => {
+                self.mark_reachable();
+                return Ok(());
+            }
+            Ok(Err(e)) => format!("Could not connect to {}:{}: {}", self.host, self.port, e),
+            Err(_) => format!(
+                "Could not connect to {}:{} within {} ms",
+                self.host,
+                self.port,
+                self.timeout.as_millis()
+            ),
         };
+        Err(BackendError::timed_out(TimedOut::Connect(message)))
+    }
 
-        match self.inner.get_object(&request).await {
-            Ok(response) => {
-                if let Some(kv) = response.value {
-                    let storable =
-                        Storable::decode(&kv.value[..]).map_err(|e| VssError::GetError {
-                            error_details: format!("Failed to decode storable: {}", e),
-                        })?;
-
-                    let (decrypted_value, _) = self
-                        .storable_builder
-                        .deconstruct(storable)
-                        .map_err(|e| VssError::GetError {
-                            error_details: format!("Failed to decrypt data: {}", e),
-                        })?;
-
-                    Ok(Some(VssItem {
-                        key: key.clone(),
-                        value: decrypted_value,
-                        version: kv.version,
-                    }))
-                } else {
-                    Ok(None)
+    fn mark_reachable(&self) {
+        *self.reachable_at.lock().unwrap() = Some(Instant::now());
+    }
+}
+
+/// Start of the TLS error a pinned client fails with when the server matches no pin.
+/// reqwest includes it in its error message, which is how `convert_error` recognizes it.
+const PIN_MISMATCH_ERROR: &str = "server certificate matches no pinned certificate";
+
+/// Accepts a server only if its certificate, or a certificate in its chain, is pinned.
+///
+/// A pinned leaf is trusted on its own; the handshake still proves the server holds its
+/// key. Anyone can send an intermediate certificate along, so a pin that only matches one
+/// also requires the chain to verify against the WebPKI roots.
+pub(crate) struct PinnedCertVerifier {
+    certificates: Vec<Vec<u8>>,
+    spki_hashes: Vec<[u8; 32]>,
+    webpki: rustls::client::WebPkiVerifier,
+}
+
+impl PinnedCertVerifier {
+    /// Decodes the pins, failing with InvalidData for malformed ones or none at all
+    fn new(pins: Vec<CertificatePin>) -> Result<Self, VssError> {
+        let invalid = |details: &str| VssError::InvalidData { error_details: details.to_string() };
+        if pins.is_empty() {
+            return Err(invalid("At least one certificate pin is required"));
+        }
+
+        let mut certificates = Vec::new();
+        let mut spki_hashes = Vec::new();
+        for pin in pins {
+            match pin {
+                CertificatePin::Certificate { encoded } if encoded.starts_with(b"-----BEGIN") => {
+                    let pem = rustls_pemfile::certs(&mut encoded.as_slice())
+                        .map_err(|_| invalid("Invalid PEM certificate pin"))?;
+                    if pem.is_empty() {
+                        return Err(invalid("PEM certificate pin holds no certificate"));
+                    }
+                    certificates.extend(pem);
+                }
+                CertificatePin::Certificate { encoded } => certificates.push(encoded),
+                CertificatePin::SpkiSha256 { hash } => {
+                    let hash = hash
+                        .try_into()
+                        .map_err(|_| invalid("SPKI pin must be a 32-byte SHA-256 hash"))?;
+                    spki_hashes.push(hash);
                 }
             }
-            Err(ExternalVssError::NoSuchKeyError(_)) => Ok(None),
-            Err(e) => Err(convert_error(e, "get")),
         }
+        if certificates.iter().any(|cert| certificate_spki(cert).is_none()) {
+            return Err(invalid("Certificate pin is not a DER or PEM encoded X.509 certificate"));
+        }
+
+        let mut roots = rustls::RootCertStore::empty();
+        roots.add_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.iter().map(|anchor| {
+            rustls::OwnedTrustAnchor::from_subject_spki_name_constraints(
+                anchor.subject,
+                anchor.spki,
+                anchor.name_constraints,
+            )
+        }));
+        let webpki = rustls::client::WebPkiVerifier::new(roots, None);
+
+        Ok(Self { certificates, spki_hashes, webpki })
     }
 
-    /// Lists all items, optionally filtered by key prefix.
+    fn matches(&self, certificate: &rustls::Certificate) -> bool {
+        self.certificates.contains(&certificate.0)
+            || certificate_spki(&certificate.0).is_some_and(|spki| {
+                self.spki_hashes.contains(sha256::Hash::hash(spki).as_byte_array())
+            })
+    }
+}
+
+impl rustls::client::ServerCertVerifier for PinnedCertVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &rustls::Certificate,
+        intermediates: &[rustls::Certificate],
+        server_name: &rustls::ServerName,
+        scts: &mut dyn Iterator<Item = &[u8]>,
+        ocsp_response: &[u8],
+        now: std::time::SystemTime,
+    ) -> Result<rustls::client::ServerCertVerified, rustls::Error> {
+        if self.matches(end_entity) {
+            return Ok(rustls::client::ServerCertVerified::assertion());
+        }
+        if intermediates.iter().any(|certificate| self.matches(certificate)) {
+            return self.webpki.verify_server_cert(
+                end_entity,
+                intermediates,
+                server_name,
+                scts,
+                ocsp_response,
+                now,
+            );
+        }
+        Err(rustls::Error::General(format!("{} ({:?})", PIN_MISMATCH_ERROR, server_name)))
+    }
+}
+
+/// Returns the DER encoded SubjectPublicKeyInfo of an X.509 certificate, or None if the
+/// certificate doesn't parse that far
+fn certificate_spki(certificate: &[u8]) -> Option<&[u8]> {
+    // Certificate ::= SEQUENCE { tbsCertificate SEQUENCE { [0] version OPTIONAL,
+    //     serialNumber, signature, issuer, validity, subject, subjectPublicKeyInfo, .. } .. }
+    let (certificate, _) = der_element(certificate)?;
+    let (tbs, _) = der_element(der_contents(certificate)?)?;
+    let mut fields = der_contents(tbs)?;
+    if fields.first() == Some(&0xa0) {
+        fields = der_element(fields)?.1;
+    }
+    for _ in 0..5 {
+        fields = der_element(fields)?.1;
+    }
+    Some(der_element(fields)?.0)
+}
+
+/// Splits the first DER element, header included, off `input`
+fn der_element(input: &[u8]) -> Option<(&[u8], &[u8])> {
+    let (header_len, len) = der_header(input)?;
+    let end = header_len.checked_add(len)?;
+    (input.len() >= end).then(|| input.split_at(end))
+}
+
+/// The contents of a single DER element, without its header
+fn der_contents(element: &[u8]) -> Option<&[u8]> {
+    let (header_len, _) = der_header(element)?;
+    element.get(header_len..)
+}
+
+/// Reads a DER tag and length, returning the header length and the content length
+fn der_header(input: &[u8]) -> Option<(usize, usize)> {
+    let &first = input.get(1)?;
+    if first < 0x80 {
+        return Some((2, first as usize));
+    }
+    let count = (first & 0x7f) as usize;
+    if count == 0 || count > 4 {
+        return None;
+    }
+    let len = input.get(2..2 + count)?.iter().fold(0, |len, &byte| len << 8 | byte as usize);
+    Some((2 + count, len))
+}
+
+/// Runs a remote request to the `operation` endpoint, attaching its attempt count and
+/// elapsed time on failure
+pub(crate) async fn tracked<T>(
+    operation: &'static str,
+    request: impl Future<Output = Result<T, ExternalVssError>>,
+) -> Result<T, BackendError> {
+    let started = Instant::now();
+    let state = RequestState { operation, failed_attempts: Cell::new(0) };
+    CURRENT_REQUEST
+        .scope(state, async {
+            request.await.map_err(|error| BackendError {
+                error,
+                attempts: Some(CURRENT_REQUEST.with(|request| request.failed_attempts.get())),
+                elapsed_ms: Some(started.elapsed().as_millis() as u64),
+                timed_out: None,
+            })
+        })
+        .await
+}
+
+/// A value read by `get_cached`, with when it was fetched or last revalidated.
+#[derive(Clone)]
+struct CachedItem {
+    item: VssItem,
+    fetched_at: Instant,
+}
+
+/// A map holding at most `capacity` entries, dropping the least recently used one when
+/// a new key would exceed it.
+pub(crate) struct LruMap<V> {
+    capacity: usize,
+    entries: HashMap<String, (u64, V)>,
+    /// Keys by the tick of their last use, oldest first
+    order: BTreeMap<u64, String>,
+    next_tick: u64,
+}
+
+impl<V: Clone> LruMap<V> {
+    pub(crate) fn new(capacity: usize) -> Self {
+        LruMap {
+            capacity,
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+            next_tick: 0,
+        }
+    }
+
+    fn tick(&mut self) -> u64 {
+        self.next_tick += 1;
+        self.next_tick
+    }
+
+    /// Returns the value for `key`, marking it as recently used
+    pub(crate) fn get(&mut self, key: &str) -> Option<V> {
+        let tick = self.tick();
+        let (used, value) = self.entries.get_mut(key)?;
+        let key = self.order.remove(used).expect("ordered entry");
+        *used = tick;
+        self.order.insert(tick, key);
+        Some(value.clone())
+    }
+
+    pub(crate) fn insert(&mut self, key: String, value: V) {
+        let tick = self.tick();
+        if let Some((used, _)) = self.entries.insert(key.clone(), (tick, value)) {
+            self.order.remove(&used);
+        } else if self.entries.len() > self.capacity {
+            if let Some((_, oldest)) = self.order.pop_first() {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.order.insert(tick, key);
+    }
+
+    pub(crate) fn remove(&mut self, key: &str) {
+        if let Some((used, _)) = self.entries.remove(key) {
+            self.order.remove(&used);
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+    }
+
+    #[cfg(test)]
+    pub(crate) fn len(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+/// Values waiting to be written by `store_debounced`, keyed by full user key.
+#[derive(Default)]
+struct DebouncedWrites {
+    pending: HashMap<String, PendingWrite>,
+    next_generation: u64,
+}
+
+struct PendingWrite {
+    value: Vec<u8>,
+    /// Identifies the call that scheduled the value, so the timer of an earlier call
+    /// doesn't write it before its own delay has passed
+    generation: u64,
+}
+
+/// The last write this client made to a storage key, used for read-after-write checks.
+#[derive(Clone)]
+enum WriteMarker {
+    /// The key was written with these stored bytes. `version` is the resulting
+    /// version when the write was conditional and it is therefore known.
+    Stored { digest: [u8; 32], version: Option<i64> },
+    Deleted,
+}
+
+impl WriteMarker {
+    /// Whether an object read from the server reflects this write or a later one
+    fn is_reflected_by(&self, stored: Option<&ExternalKeyValue>) -> bool {
+        match (self, stored) {
+            (WriteMarker::Stored { digest, version }, Some(kv)) => {
+                sha256::Hash::hash(&kv.value).to_byte_array() == *digest
+                    || version.is_some_and(|version| kv.version >= version)
+            }
+            (WriteMarker::Deleted, None) => true,
+            _ => false,
+        }
+    }
+}
+
+/// The outcome of reading a single key as part of a batch.
+type FetchResult = Result<Option<VssItem>, VssError>;
+
+/// Where a client's requests are sent.
+pub(crate) enum Backend {
+    /// A VSS server reached over HTTP. `auth` is set when requests are authenticated
+    /// with a token that can be re-minted after the server rejects it.
+    Remote {
+        client: ExternalVssClient<CustomRetryPolicy>,
+        auth: Option<Arc<RefreshableHeaderProvider>>,
+        timeouts: RequestTimeouts,
+        retry_listener: RetryListenerSlot,
+    },
+    /// A process-local store, for tests that shouldn't need a server.
+    #[cfg(any(test, feature = "test-utils"))]
+    InMemory(InMemoryStore),
+}
+
+impl Backend {
+    async fn get_object(
+        &self,
+        request: &GetObjectRequest,
+    ) -> Result<GetObjectResponse, BackendError> {
+        match self {
+            Backend::Remote { client, auth, timeouts, .. } => {
+                let request =
+                    with_reauth(auth.as_deref(), || tracked("getObject", client.get_object(request)));
+                timeouts.run(request).await
+            }
+            #[cfg(any(test, feature = "test-utils"))]
+            Backend::InMemory(store) => Ok(store.get_object(request)?),
+        }
+    }
+
+    async fn put_object(
+        &self,
+        request: &PutObjectRequest,
+    ) -> Result<PutObjectResponse, BackendError> {
+        match self {
+            Backend::Remote { client, auth, timeouts, .. } => {
+                let request =
+                    with_reauth(auth.as_deref(), || tracked("putObjects", client.put_object(request)));
+                timeouts.run(request).await
+            }
+            #[cfg(any(test, feature = "test-utils"))]
+            Backend::InMemory(store) => Ok(store.put_object(request)?),
+        }
+    }
+
+    async fn delete_object(
+        &self,
+        request: &DeleteObjectRequest,
+    ) -> Result<DeleteObjectResponse, BackendError> {
+        match self {
+            Backend::Remote { client, auth, timeouts, .. } => {
+                let request =
+                    with_reauth(auth.as_deref(), || tracked("deleteObject", client.delete_object(request)));
+                timeouts.run(request).await
+            }
+            #[cfg(any(test, feature = "test-utils"))]
+            Backend::InMemory(store) => Ok(store.delete_object(request)?),
+        }
+    }
+
+    async fn list_key_versions(
+        &self,
+        request: &ListKeyVersionsRequest,
+    ) -> Result<ExternalListKeyVersionsResponse, BackendError> {
+        match self {
+            Backend::Remote { client, auth, timeouts, .. } => {
+                let request =
+                    with_reauth(auth.as_deref(), || tracked("listKeyVersions", client.list_key_versions(request)));
+                timeouts.run(request).await
+            }
+            #[cfg(any(test, feature = "test-utils"))]
+            Backend::InMemory(store) => Ok(store.list_key_versions(request)?),
+        }
+    }
+}
+
+/// Creates the header provider a `RefreshableHeaderProvider` currently delegates to
+type HeaderProviderFactory =
+    Box<dyn Fn() -> Result<Arc<dyn VssHeaderProvider>, VssHeaderProviderError> + Send + Sync>;
+
+/// A header provider whose cached credentials can be dropped.
+///
+/// `LnurlAuthToJwtProvider` only re-mints its JWT once the token's own expiry passes, so a
+/// token the server rejects earlier would keep failing. Refreshing swaps in a new provider,
+/// which mints a fresh token on its first request.
+pub(crate) struct RefreshableHeaderProvider {
+    make_provider: HeaderProviderFactory,
+    current: RwLock<Arc<dyn VssHeaderProvider>>,
+}
+
+impl RefreshableHeaderProvider {
+    pub(crate) fn new(make_provider: HeaderProviderFactory) -> Result<Self, VssHeaderProviderError> {
+        let current = RwLock::new(make_provider()?);
+        Ok(Self { make_provider, current })
+    }
+
+    /// Replaces the current provider with a fresh one
+    pub(crate) fn refresh(&self) -> Result<(), VssHeaderProviderError> {
+        let provider = (self.make_provider)()?;
+        *self.current.write().unwrap() = provider;
+        Ok(())
+    }
+
+    /// Requests headers without sending anything, so the current provider mints and caches
+    /// a token if it has no valid one. Rejected credentials become `AuthError`, any other
+    /// failure, such as an unreachable auth server, `ConnectionError`.
+    pub(crate) async fn warm(&self) -> Result<(), VssError> {
+        match self.get_headers(&[]).await {
+            Ok(_) => Ok(()),
+            Err(VssHeaderProviderError::AuthorizationError { error }) => {
+                Err(VssError::AuthError { error_details: error })
+            }
+            Err(e) => Err(VssError::ConnectionError {
+                error_details: format!("Failed to refresh auth token: {}", e),
+            }),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl VssHeaderProvider for RefreshableHeaderProvider {
+    async fn get_headers(
+        &self,
+        request: &[u8],
+    ) -> Result<HashMap<String, String>, VssHeaderProviderError> {
+        let provider = self.current.read().unwrap().clone();
+        provider.get_headers(request).await
+    }
+}
+
+/// Header carrying the correlation ID of a request
+pub(crate) const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Adds an `x-request-id` header of the form `{correlation_id}-{sequence}` to the headers
+/// of `inner`. The sequence increases with every request sent, including retries.
+pub(crate) struct CorrelationHeaderProvider {
+    inner: Arc<dyn VssHeaderProvider>,
+    correlation_id: String,
+    sequence: AtomicU64,
+}
+
+impl CorrelationHeaderProvider {
+    pub(crate) fn new(inner: Arc<dyn VssHeaderProvider>, correlation_id: String) -> Self {
+        Self { inner, correlation_id, sequence: AtomicU64::new(0) }
+    }
+}
+
+#[async_trait::async_trait]
+impl VssHeaderProvider for CorrelationHeaderProvider {
+    async fn get_headers(
+        &self,
+        request: &[u8],
+    ) -> Result<HashMap<String, String>, VssHeaderProviderError> {
+        let mut headers = self.inner.get_headers(request).await?;
+        let sequence = self.sequence.fetch_add(1, Ordering::Relaxed) + 1;
+        headers.insert(
+            REQUEST_ID_HEADER.to_string(),
+            format!("{}-{}", self.correlation_id, sequence),
+        );
+        Ok(headers)
+    }
+}
+
+/// User agent sent when `VssClientConfig::user_agent` is unset
+pub(crate) const DEFAULT_USER_AGENT: &str =
+    concat!("vss-rust-client-ffi/", env!("CARGO_PKG_VERSION"));
+
+/// Adds a `User-Agent` header to the headers of `inner`, unless `inner` already sets one.
+pub(crate) struct UserAgentHeaderProvider {
+    inner: Arc<dyn VssHeaderProvider>,
+    user_agent: String,
+}
+
+impl UserAgentHeaderProvider {
+    pub(crate) fn new(inner: Arc<dyn VssHeaderProvider>, user_agent: String) -> Self {
+        Self { inner, user_agent }
+    }
+}
+
+#[async_trait::async_trait]
+impl VssHeaderProvider for UserAgentHeaderProvider {
+    async fn get_headers(
+        &self,
+        request: &[u8],
+    ) -> Result<HashMap<String, String>, VssHeaderProviderError> {
+        let mut headers = self.inner.get_headers(request).await?;
+        if !headers.keys().any(|name| name.eq_ignore_ascii_case(USER_AGENT.as_str())) {
+            headers.insert(USER_AGENT.to_string(), self.user_agent.clone());
+        }
+        Ok(headers)
+    }
+}
+
+/// Runs a remote request and, if the server rejects its credentials, refreshes `auth`
+/// and retries once. The `AuthError` is returned if there is nothing to refresh or the
+/// retried request is rejected as well, e.g. because minting a new token failed.
+pub(crate) async fn with_reauth<T, Fut>(
+    auth: Option<&RefreshableHeaderProvider>,
+    request: impl Fn() -> Fut,
+) -> Result<T, BackendError>
+where
+    Fut: Future<Output = Result<T, BackendError>>,
+{
+    let result = request().await;
+    let Some(auth) = auth else {
+        return result;
+    };
+
+    match result {
+        Err(BackendError { error: ExternalVssError::AuthError(_), .. }) => {
+            auth.refresh()
+                .map_err(|e| ExternalVssError::AuthError(format!("Re-authentication failed: {}", e)))?;
+            request().await
+        }
+        other => other,
+    }
+}
+
+/// Builds the retry policy for a client's requests according to its config.
+///
+/// With `disable_retries` set, every request is attempted exactly once.
+pub(crate) fn build_retry_policy(config: &VssClientConfig) -> CustomRetryPolicy {
+    let max_attempts = if config.disable_retries { 1 } else { 10 };
+    let retry_policy = ExponentialBackoffRetryPolicy::new(Duration::from_millis(10))
+        .with_max_attempts(max_attempts)
+        .with_max_total_delay(Duration::from_secs(15))
+        .with_max_jitter(Duration::from_millis(10))
+        .skip_retry_on_error(Box::new(|e: &ExternalVssError| {
+            RATE_LIMITED.with(|limited| limited.set(is_rate_limited(e)));
+            !is_retryable_error(e)
+        }) as _);
+
+    TrackedRetryPolicy::new(retry_policy)
+}
+
+/// The source of the nonces used when encrypting values.
+#[derive(Clone)]
+pub(crate) enum ClientEntropySource {
+    /// Randomness from [`rand`].
+    Rand,
+    /// A caller-provided source, for tests that need reproducible ciphertext.
+    #[cfg(any(test, feature = "test-utils"))]
+    Custom(Arc<dyn EntropySource + Send + Sync>),
+}
+
+impl EntropySource for ClientEntropySource {
+    fn fill_bytes(&self, buffer: &mut [u8]) {
+        match self {
+            ClientEntropySource::Rand => rand::thread_rng().fill_bytes(buffer),
+            #[cfg(any(test, feature = "test-utils"))]
+            ClientEntropySource::Custom(source) => source.fill_bytes(buffer),
+        }
+    }
+}
+
+/// A `VssClient::rotate_encryption` that stopped before every item was re-encrypted.
+///
+/// Calling `rotate_encryption` again with the same seed resumes where this one stopped.
+pub struct RotationError {
+    /// Why the rotation stopped
+    pub error: VssError,
+    /// Items re-encrypted before the failure; they only decrypt under the new keys
+    pub rotated: u32,
+    /// A client with the new keys, which reads the items rotated so far. None when the
+    /// rotation failed before the new keys were derived.
+    pub rotated_client: Option<VssClient>,
+}
+
+impl From<VssError> for RotationError {
+    fn from(error: VssError) -> Self {
+        RotationError { error, rotated: 0, rotated_client: None }
+    }
+}
+
+impl From<RotationError> for VssError {
+    fn from(error: RotationError) -> Self {
+        error.error
+    }
+}
+
+impl std::fmt::Debug for RotationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RotationError")
+            .field("error", &self.error)
+            .field("rotated", &self.rotated)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A VSS client bound to a single store.
+///
+/// Clones share the same underlying HTTP client, so connections (and their TLS
+/// sessions) are pooled and kept alive across all operations made through any clone.
+/// `vss-client` builds its HTTP client internally and does not accept a pre-configured
+/// one together with a header provider, so pool settings use reqwest's defaults
+/// (idle connections are kept for 90 seconds, with no per-host limit).
+#[derive(Clone)]
+pub struct VssClient {
+    inner: Arc<Backend>,
+    store_id: String,
+    storable_builder: Arc<StorableBuilder<ClientEntropySource>>,
+    entropy: ClientEntropySource,
+    key_obfuscator: Option<Arc<KeyObfuscator>>,
+    encrypted: bool,
+    config: VssClientConfig,
+    key_prefix: String,
+    last_writes: Arc<Mutex<LruMap<WriteMarker>>>,
+    debounced: Arc<Mutex<DebouncedWrites>>,
+    cache: Arc<Mutex<LruMap<CachedItem>>>,
+}
+
+impl VssClient {
+    /// Creates a new VSS client instance.
+    ///
+    /// # Parameters
+    /// - `base_url`: The VSS server URL
+    /// - `store_id`: The storage namespace identifier
+    ///
+    /// # Returns
+    /// A new VssClient instance or VssError on failure
+    pub async fn new(base_url: String, store_id: String) -> Result<Self, VssError> {
+        Self::new_with_config(base_url, store_id, VssClientConfig::default()).await
+    }
+
+    /// Creates a new VSS client instance with a custom configuration.
+    ///
+    /// # Parameters
+    /// - `base_url`: The VSS server URL
+    /// - `store_id`: The storage namespace identifier
+    /// - `config`: Client behaviour settings
+    ///
+    /// # Returns
+    /// A new VssClient instance or VssError on failure
+    pub async fn new_with_config(
+        base_url: String,
+        store_id: String,
+        config: VssClientConfig,
+    ) -> Result<Self, VssError> {
+        let header_provider = Arc::new(FixedHeaders::new(HashMap::new()));
+
+        Self::new_with_header_provider(base_url, store_id, header_provider, None, None, None, config)
+            .await
+    }
+
+    /// Creates a new VSS client without authentication that only talks to a server
+    /// presenting one of the pinned certificates or public keys.
+    ///
+    /// A pin matching the server's own certificate is trusted on its own, so self-signed
+    /// certificates can be pinned. A pin matching only an intermediate certificate also
+    /// requires the chain to verify against the usual WebPKI roots. Requests to a server
+    /// matching no pin fail with `VssError::ConnectionError` and aren't retried.
+    ///
+    /// # Parameters
+    /// - `base_url`: The VSS server URL, which must use https
+    /// - `store_id`: The storage namespace identifier
+    /// - `pins`: The accepted certificates or SubjectPublicKeyInfo hashes, at least one
+    /// - `config`: Client behaviour settings. `correlation_id` can't be combined with pinning
+    ///
+    /// # Returns
+    /// A new VssClient instance, or InvalidData for a malformed pin or an http base URL
+    pub async fn new_with_pinned_certificates(
+        base_url: String,
+        store_id: String,
+        pins: Vec<CertificatePin>,
+        config: VssClientConfig,
+    ) -> Result<Self, VssError> {
+        if !base_url.trim().starts_with("https://") {
+            return Err(VssError::InvalidData {
+                error_details: "Certificate pinning requires an https base URL".to_string(),
+            });
+        }
+        let verifier = PinnedCertVerifier::new(pins)?;
+        let header_provider = Arc::new(FixedHeaders::new(HashMap::new()));
+
+        Self::new_with_header_provider(
+            base_url,
+            store_id,
+            header_provider,
+            None,
+            None,
+            Some(verifier),
+            config,
+        )
+        .await
+    }
+
+    /// Creates a new VSS client instance with LNURL-auth.
+    ///
+    /// # Parameters
+    /// - `base_url`: The VSS server URL
+    /// - `store_id`: The storage namespace identifier
+    /// - `seed`: The seed bytes for key derivation (32 bytes)
+    /// - `lnurl_auth_server_url`: The LNURL-auth server URL
+    ///
+    /// # Returns
+    /// A new VssClient instance or VssError on failure
+    pub async fn new_with_lnurl_auth(
+        base_url: String,
+        store_id: String,
+        seed: [u8; 32],
+        lnurl_auth_server_url: String,
+    ) -> Result<Self, VssError> {
+        Self::new_with_lnurl_auth_and_config(
+            base_url,
+            store_id,
+            seed,
+            lnurl_auth_server_url,
+            VssClientConfig::default(),
+        )
+        .await
+    }
+
+    /// Creates a new VSS client instance with LNURL-auth and a custom configuration.
+    ///
+    /// Values are always encrypted. With `config.obfuscate_keys` set to false, keys are
+    /// sent in plaintext instead of obfuscated, which exposes every key name to the server
+    /// operator and anyone with access to its storage.
+    ///
+    /// # Parameters
+    /// - `base_url`: The VSS server URL
+    /// - `store_id`: The storage namespace identifier
+    /// - `seed`: The seed bytes for key derivation (32 bytes)
+    /// - `lnurl_auth_server_url`: The LNURL-auth server URL
+    /// - `config`: Client behaviour settings
+    ///
+    /// # Returns
+    /// A new VssClient instance or VssError on failure
+    pub async fn new_with_lnurl_auth_and_config(
+        base_url: String,
+        store_id: String,
+        seed: [u8; 32],
+        lnurl_auth_server_url: String,
+        config: VssClientConfig,
+    ) -> Result<Self, VssError> {
+        let vss_xprv = derive_vss_xprv(&SECP, &seed)?;
+
+        let lnurl_auth_xprv = vss_xprv
+            .derive_priv(
+                &SECP,
+                &[ChildNumber::Hardened {
+                    index: VSS_LNURL_AUTH_HARDENED_CHILD_INDEX,
+                }],
+            )
+            .map_err(|e| VssError::ConnectionError {
+                error_details: format!("Failed to derive LNURL-auth key: {}", e),
+            })?;
+
+        // A new provider starts without a cached JWT, so refreshing re-mints the token
+        let make_provider: HeaderProviderFactory = Box::new(move || {
+            let provider = LnurlAuthToJwtProvider::new(
+                lnurl_auth_xprv,
+                lnurl_auth_server_url.clone(),
+                HashMap::new(),
+            )?;
+            Ok(Arc::new(provider) as Arc<dyn VssHeaderProvider>)
+        });
+        let auth = RefreshableHeaderProvider::new(make_provider).map_err(|e| {
+            VssError::ConnectionError {
+                error_details: format!("Failed to create LNURL-auth provider: {}", e),
+            }
+        })?;
+        let auth = Arc::new(auth);
+
+        let vss_seed_bytes: [u8; 32] = vss_xprv.private_key.secret_bytes();
+
+        Self::new_with_header_provider(
+            base_url,
+            store_id,
+            auth.clone(),
+            Some(auth),
+            Some(vss_seed_bytes),
+            None,
+            config,
+        )
+        .await
+    }
+
+    /// Internal method to create a client with any header provider. `auth` is refreshed
+    /// when the server rejects the credentials it provides.
+    ///
+    /// Without `auth` or `config.correlation_id` the only header is the user agent, so the
+    /// HTTP client is built here with it and the configured timeouts. `vss-client` can't
+    /// combine such a client with a header provider, so clients with either use the one
+    /// `vss-client` builds, and `pinning` and `config.proxy_url` are unsupported for them.
+    async fn new_with_header_provider(
+        base_url: String,
+        store_id: String,
+        header_provider: Arc<dyn VssHeaderProvider>,
+        auth: Option<Arc<RefreshableHeaderProvider>>,
+        vss_seed: Option<[u8; 32]>,
+        pinning: Option<PinnedCertVerifier>,
+        config: VssClientConfig,
+    ) -> Result<Self, VssError> {
+        let base_url = normalize_base_url(&base_url, config.allow_empty_base_url)?;
+        validate_config(&config)?;
+        let proxy = config.proxy_url.as_deref().map(build_proxy).transpose()?;
+        let retry_policy = build_retry_policy(&config);
+        let retry_listener = retry_policy.listener_slot();
+        let user_agent = config.user_agent.as_deref().unwrap_or(DEFAULT_USER_AGENT);
+        if HeaderValue::from_str(user_agent).is_err() {
+            return Err(VssError::InvalidData {
+                error_details: format!("Invalid user agent: {:?}", user_agent),
+            });
+        }
+
+        let own_http_client = auth.is_none() && config.correlation_id.is_none();
+        let timeouts = RequestTimeouts::new(&base_url, &config, !own_http_client);
+        let client = if own_http_client {
+            let http_client = build_http_client(user_agent, &config, pinning, proxy)?;
+            ExternalVssClient::from_client(base_url, http_client, retry_policy)
+        } else {
+            let setting = match (&pinning, &proxy) {
+                (Some(_), _) => Some("certificate pinning"),
+                (None, Some(_)) => Some("proxy_url"),
+                (None, None) => None,
+            };
+            if let Some(setting) = setting {
+                let feature = match auth {
+                    Some(_) => format!("LNURL-auth with {}", setting),
+                    None => format!("correlation_id with {}", setting),
+                };
+                return Err(VssError::Unsupported { feature });
+            }
+
+            let header_provider: Arc<dyn VssHeaderProvider> =
+                Arc::new(UserAgentHeaderProvider::new(header_provider, user_agent.to_string()));
+            let header_provider: Arc<dyn VssHeaderProvider> = match config.correlation_id {
+                Some(ref correlation_id) => {
+                    Arc::new(CorrelationHeaderProvider::new(header_provider, correlation_id.clone()))
+                }
+                None => header_provider,
+            };
+            ExternalVssClient::new_with_headers(base_url, retry_policy, header_provider)
+        };
+        let validate = config.validate_on_init;
+
+        let client = Self::from_backend(
+            Backend::Remote { client, auth, timeouts, retry_listener },
+            store_id,
+            vss_seed,
+            config,
+            ClientEntropySource::Rand,
+        );
+        if client.config.verify_key_obfuscation_on_init {
+            client.verify_key_obfuscation()?;
+        }
+        if validate {
+            client.health_check().await?;
+        }
+
+        Ok(client)
+    }
+
+    /// Creates a client backed by an in-memory store instead of a VSS server.
+    ///
+    /// Behaves like a server-backed client without encryption, which makes the full
+    /// store/get/list/delete path testable without a network. Data lives as long as
+    /// the client and its clones.
+    ///
+    /// # Parameters
+    /// - `store_id`: The storage namespace identifier
+    ///
+    /// # Returns
+    /// A new VssClient instance
+    #[cfg(any(test, feature = "test-utils"))]
+    pub fn new_in_memory(store_id: String) -> Self {
+        Self::from_backend(
+            Backend::InMemory(InMemoryStore::new()),
+            store_id,
+            None,
+            VssClientConfig::default(),
+            ClientEntropySource::Rand,
+        )
+    }
+
+    /// Creates a client backed by an in-memory store, with encryption and key
+    /// obfuscation derived from `seed` as for LNURL-auth clients.
+    ///
+    /// # Parameters
+    /// - `store_id`: The storage namespace identifier
+    /// - `seed`: The seed bytes for key derivation (32 bytes)
+    ///
+    /// # Returns
+    /// A new VssClient instance or VssError on failure
+    #[cfg(any(test, feature = "test-utils"))]
+    pub fn new_in_memory_with_seed(store_id: String, seed: [u8; 32]) -> Result<Self, VssError> {
+        Self::new_in_memory_with_seed_and_config(store_id, seed, VssClientConfig::default())
+    }
+
+    /// Creates a client backed by an in-memory store, with encryption derived from `seed`
+    /// and a custom configuration.
+    ///
+    /// # Parameters
+    /// - `store_id`: The storage namespace identifier
+    /// - `seed`: The seed bytes for key derivation (32 bytes)
+    /// - `config`: Client behaviour settings
+    ///
+    /// # Returns
+    /// A new VssClient instance or VssError on failure
+    #[cfg(any(test, feature = "test-utils"))]
+    pub fn new_in_memory_with_seed_and_config(
+        store_id: String,
+        seed: [u8; 32],
+        config: VssClientConfig,
+    ) -> Result<Self, VssError> {
+        Self::in_memory_with_seed(store_id, seed, config, ClientEntropySource::Rand)
+    }
+
+    /// Creates a client backed by an in-memory store, with encryption derived from `seed`
+    /// and encryption nonces drawn from `entropy`.
+    ///
+    /// With a deterministic `entropy`, the same writes produce byte-identical stored
+    /// values, which allows snapshot tests of the exact storage layout. Never use a
+    /// predictable source outside tests: reusing nonces breaks the encryption.
+    ///
+    /// # Parameters
+    /// - `store_id`: The storage namespace identifier
+    /// - `seed`: The seed bytes for key derivation (32 bytes)
+    /// - `entropy`: The source of encryption nonces
+    ///
+    /// # Returns
+    /// A new VssClient instance or VssError on failure
+    #[cfg(any(test, feature = "test-utils"))]
+    pub fn new_in_memory_with_entropy(
+        store_id: String,
+        seed: [u8; 32],
+        entropy: Arc<dyn EntropySource + Send + Sync>,
+    ) -> Result<Self, VssError> {
+        Self::in_memory_with_seed(
+            store_id,
+            seed,
+            VssClientConfig::default(),
+            ClientEntropySource::Custom(entropy),
+        )
+    }
+
+    #[cfg(any(test, feature = "test-utils"))]
+    fn in_memory_with_seed(
+        store_id: String,
+        seed: [u8; 32],
+        config: VssClientConfig,
+        entropy: ClientEntropySource,
+    ) -> Result<Self, VssError> {
+        validate_config(&config)?;
+        let vss_xprv = derive_vss_xprv(&SECP, &seed)?;
+
+        let client = Self::from_backend(
+            Backend::InMemory(InMemoryStore::new()),
+            store_id,
+            Some(vss_xprv.private_key.secret_bytes()),
+            config,
+            entropy,
+        );
+        if client.config.verify_key_obfuscation_on_init {
+            client.verify_key_obfuscation()?;
+        }
+        Ok(client)
+    }
+
+    /// Internal method to set up encryption around any backend
+    fn from_backend(
+        backend: Backend,
+        store_id: String,
+        vss_seed: Option<[u8; 32]>,
+        config: VssClientConfig,
+        entropy: ClientEntropySource,
+    ) -> Self {
+        let encrypted = vss_seed.is_some();
+        let (storable_builder, key_obfuscator) = if let Some(seed) = vss_seed {
+            let (data_encryption_key, obfuscation_master_key) =
+                derive_data_encryption_and_obfuscation_keys(&seed, &config);
+            let builder = Arc::new(StorableBuilder::new(data_encryption_key, entropy.clone()));
+            let obfuscator = config
+                .obfuscate_keys
+                .then(|| Arc::new(KeyObfuscator::new(obfuscation_master_key)));
+            (builder, obfuscator)
+        } else {
+            let zero_key = [0u8; 32];
+            let builder = Arc::new(StorableBuilder::new(zero_key, entropy.clone()));
+            (builder, None)
+        };
+
+        VssClient {
+            inner: Arc::new(backend),
+            store_id,
+            storable_builder,
+            entropy,
+            key_obfuscator,
+            encrypted,
+            config,
+            key_prefix: String::new(),
+            last_writes: Arc::new(Mutex::new(LruMap::new(MAX_TRACKED_WRITES))),
+            debounced: Arc::new(Mutex::new(DebouncedWrites::default())),
+            cache: Arc::new(Mutex::new(LruMap::new(MAX_CACHED_ITEMS))),
+        }
+    }
+
+    /// Returns a view of this client scoped to keys under `prefix`.
+    ///
+    /// The view prepends `prefix` to every user key before obfuscation and strips it from
+    /// keys it returns, so `store("theme")` on a `settings/` view writes `settings/theme`.
+    /// Listing on the view only returns keys within the prefix. Views can be nested, and
+    /// share the connection and encryption keys of the client they were created from.
+    ///
+    /// # Parameters
+    /// - `prefix`: The key prefix to scope the view to
+    ///
+    /// # Returns
+    /// A new VssClient view
+    pub fn with_prefix(&self, prefix: String) -> Arc<VssClient> {
+        let mut view = self.clone();
+        view.key_prefix.push_str(&prefix);
+        Arc::new(view)
+    }
+
+    /// Returns whether values are encrypted with a seed-derived key.
+    ///
+    /// Clients created without a seed still wrap values in a `Storable` envelope, but under
+    /// an all-zero key, which offers no confidentiality. Those report `false`. Keys are
+    /// obfuscated as well unless `obfuscate_keys` was turned off in the config.
+    pub fn is_encrypted(&self) -> bool {
+        self.encrypted
+    }
+
+    /// Returns the storage key the server holds for a user key.
+    ///
+    /// This is the key after the view prefix is applied and, when encryption is enabled,
+    /// obfuscated. Without encryption it is the full key unchanged.
+    ///
+    /// # Parameters
+    /// - `key`: The user key
+    ///
+    /// # Returns
+    /// The key as sent to the server
+    pub fn obfuscated_key(&self, key: &str) -> Result<String, VssError> {
+        self.validate_key(key)?;
+        Ok(self.build_key(key))
+    }
+
+    /// Checks that the key obfuscator turns an obfuscated sample key back into the original.
+    ///
+    /// Runs locally, without contacting the server. A broken obfuscator would make every
+    /// key unreadable when listing, so clients can run this at startup, or automatically
+    /// with `verify_key_obfuscation_on_init`. It can't tell whether stored keys were
+    /// obfuscated with the same seed; listing such keys fails with `VssError::ListError`.
+    ///
+    /// # Returns
+    /// Ok(()) if the round trip succeeds or keys aren't obfuscated, or a ConnectionError
+    pub fn verify_key_obfuscation(&self) -> Result<(), VssError> {
+        let Some(ref obfuscator) = self.key_obfuscator else {
+            return Ok(());
+        };
+
+        let obfuscated = obfuscator.obfuscate(OBFUSCATION_CHECK_KEY);
+        match obfuscator.deobfuscate(&obfuscated) {
+            Ok(key) if key == OBFUSCATION_CHECK_KEY => Ok(()),
+            Ok(key) => Err(VssError::ConnectionError {
+                error_details: format!(
+                    "Key obfuscation self-check failed: {:?} came back as {:?}",
+                    OBFUSCATION_CHECK_KEY, key
+                ),
+            }),
+            Err(e) => Err(VssError::ConnectionError {
+                error_details: format!("Key obfuscation self-check failed: {}", e),
+            }),
+        }
+    }
+
+    /// Returns the key prefix listings send to the server for a user prefix.
+    ///
+    /// Obfuscation encrypts each key as a whole, so obfuscated keys don't share the
+    /// prefixes of the keys they hide and the server can't filter them. With obfuscated
+    /// keys this is empty: listings fetch the full key list and filter it by user key
+    /// instead, which costs a listing of the whole store. Without obfuscation it is the
+    /// prefix with the view prefix applied.
+    ///
+    /// # Parameters
+    /// - `prefix`: The user key prefix
+    ///
+    /// # Returns
+    /// The prefix sent to the server, empty if the server doesn't filter
+    pub fn resolve_storage_prefix(&self, prefix: &str) -> String {
+        self.server_key_prefix(Some(prefix)).unwrap_or_default()
+    }
+
+    /// Discovers what the server supports.
+    ///
+    /// The VSS API has no discovery endpoint, so this infers capabilities from a
+    /// single-item listing: a server that tracks a store-wide global version reports it
+    /// on the first page. The server version and batch limits can't be observed and are
+    /// left as None.
+    ///
+    /// # Returns
+    /// The inferred ServerInfo
+    pub async fn server_info(&self) -> Result<ServerInfo, VssError> {
+        let request = ListKeyVersionsRequest {
+            store_id: self.store_id.clone(),
+            key_prefix: None,
+            page_size: Some(1),
+            page_token: None,
+        };
+
+        let response = self
+            .inner
+            .list_key_versions(&request)
+            .await
+            .map_err(|e| convert_error(e, "server_info"))?;
+
+        Ok(ServerInfo {
+            version: None,
+            supports_global_version: response.global_version.is_some(),
+            max_batch_size: None,
+        })
+    }
+
+    /// Checks that the server is reachable and accepts this client's credentials.
+    ///
+    /// Sends a single-key listing request. Authentication failures are returned as
+    /// `AuthError`; any other failure, including an unreachable server, as
+    /// `ConnectionError`.
+    pub async fn health_check(&self) -> Result<(), VssError> {
+        let request = ListKeyVersionsRequest {
+            store_id: self.store_id.clone(),
+            key_prefix: None,
+            page_size: Some(1),
+            page_token: None,
+        };
+
+        match self.inner.list_key_versions(&request).await {
+            Ok(_) => Ok(()),
+            Err(e) => match convert_error(e, "health_check") {
+                VssError::AuthError { error_details } => Err(VssError::AuthError { error_details }),
+                error => Err(VssError::ConnectionError {
+                    error_details: format!("Server check failed: {}", error),
+                }),
+            },
+        }
+    }
+
+    /// Checks whether the store holds at least one key, as a sanity check on the store ID.
+    ///
+    /// A store ID with a mistyped prefix points at an empty namespace rather than failing,
+    /// so an empty store right after setup usually means the wrong ID. Lists a single key;
+    /// a view whose keys are obfuscated may have to page past keys outside its prefix.
+    ///
+    /// # Returns
+    /// true if the store, or the view's part of it, has any keys
+    pub async fn verify_store_nonempty(&self) -> Result<bool, VssError> {
+        let page_size = if self.key_prefix.is_empty() || self.key_obfuscator.is_none() {
+            1
+        } else {
+            self.page_size()
+        };
+
+        let mut page_token = None;
+        loop {
+            let page = self
+                .key_versions_page(None, Some(page_size), page_token, "verify_store_nonempty")
+                .await?;
+            if !page.key_versions.is_empty() {
+                return Ok(true);
+            }
+            match page.next_page_token {
+                Some(token) => page_token = Some(token),
+                None => return Ok(false),
+            }
+        }
+    }
+
+    /// Makes sure an LNURL-auth client holds a valid JWT, minting one if needed.
+    ///
+    /// Tokens are otherwise minted lazily by the first request after they expire, which
+    /// adds the LNURL-auth round trips to that request. A cached token that is still valid
+    /// for more than a minute is kept. Does nothing for clients without LNURL-auth.
+    pub async fn refresh_auth(&self) -> Result<(), VssError> {
+        match self.inner.as_ref() {
+            Backend::Remote { auth: Some(auth), .. } => auth.warm().await,
+            _ => Ok(()),
+        }
+    }
+
+    /// Sets the listener told about each retry of a request to the server, or removes it.
+    ///
+    /// Applies to this client and all its clones and views, which share the connection.
+    /// Clients without a server, such as in-memory ones, never retry.
+    ///
+    /// # Parameters
+    /// - `listener`: The listener to call, None to stop reporting retries
+    pub fn set_retry_listener(&self, listener: Option<Arc<dyn VssRetryListener>>) {
+        match self.inner.as_ref() {
+            Backend::Remote { retry_listener, .. } => *retry_listener.write().unwrap() = listener,
+            #[cfg(any(test, feature = "test-utils"))]
+            Backend::InMemory(_) => {}
+        }
+    }
+
+    /// Stores a key-value pair. Server manages versioning automatically.
+    ///
+    /// # Parameters
+    /// - `key`: The unique key identifier
+    /// - `value`: The binary data to store
+    ///
+    /// # Returns
+    /// VssItem with the stored data and assigned version
+    pub async fn store(&self, key: String, value: Vec<u8>) -> Result<VssItem, VssError> {
+        self.store_with_metadata(key, value, None).await
+    }
+
+    /// Stores a key-value pair once no other debounced write to the key followed for
+    /// `debounce_ms` milliseconds.
+    ///
+    /// Each call replaces the key's pending value and restarts its delay, so a burst of
+    /// calls results in a single write of the last value. The write is made by a task
+    /// spawned on the current Tokio runtime, so this must be called within one. A failed
+    /// write stays pending and is retried, and reported, by the next `flush`. Reads don't
+    /// see pending values.
+    ///
+    /// # Parameters
+    /// - `key`: The unique key identifier
+    /// - `value`: The binary data to store
+    /// - `debounce_ms`: How long the key must go without further writes before it is stored
+    ///
+    /// # Returns
+    /// Ok once the write is scheduled, or InvalidData for an invalid key
+    pub fn store_debounced(
+        &self,
+        key: String,
+        value: Vec<u8>,
+        debounce_ms: u64,
+    ) -> Result<(), VssError> {
+        let write = self.schedule_debounced(key, value, debounce_ms)?;
+        tokio::spawn(write);
+        Ok(())
+    }
+
+    /// Records a debounced value, returning the future that writes it once its delay has
+    /// passed, unless a later call or a flush took it over
+    pub(crate) fn schedule_debounced(
+        &self,
+        key: String,
+        value: Vec<u8>,
+        debounce_ms: u64,
+    ) -> Result<impl Future<Output = ()> + Send + 'static, VssError> {
+        self.validate_write_key(&key)?;
+
+        let full_key = self.full_key(&key);
+        let generation = {
+            let mut debounced = self.debounced.lock().unwrap();
+            debounced.next_generation += 1;
+            let generation = debounced.next_generation;
+            debounced
+                .pending
+                .insert(full_key.clone(), PendingWrite { value, generation });
+            generation
+        };
+
+        let client = self.root();
+        Ok(async move {
+            tokio::time::sleep(Duration::from_millis(debounce_ms)).await;
+
+            let write = {
+                let mut debounced = client.debounced.lock().unwrap();
+                let current = debounced.pending.get(&full_key).map(|write| write.generation);
+                if current != Some(generation) {
+                    return;
+                }
+                debounced.pending.remove(&full_key).expect("pending write exists")
+            };
+
+            if client.store(full_key.clone(), write.value.clone()).await.is_err() {
+                client.restore_pending(vec![(full_key, write)]);
+            }
+        })
+    }
+
+    /// Writes all values pending from `store_debounced` now, including those scheduled
+    /// through other views of this client.
+    ///
+    /// Values are written one by one. On the first failure the values not yet written stay
+    /// pending and the error is returned.
+    ///
+    /// # Returns
+    /// The number of values written
+    pub async fn flush(&self) -> Result<u32, VssError> {
+        self.check_writable()?;
+        let pending: Vec<(String, PendingWrite)> =
+            self.debounced.lock().unwrap().pending.drain().collect();
+        let root = self.root();
+
+        let mut written = 0;
+        let mut remaining = pending.into_iter();
+        while let Some((key, write)) = remaining.next() {
+            if let Err(e) = root.store(key.clone(), write.value.clone()).await {
+                root.restore_pending(std::iter::once((key, write)).chain(remaining).collect());
+                return Err(e);
+            }
+            written += 1;
+        }
+        Ok(written)
+    }
+
+    /// Puts unwritten debounced values back, unless newer values were scheduled meanwhile
+    fn restore_pending(&self, writes: Vec<(String, PendingWrite)>) {
+        let mut debounced = self.debounced.lock().unwrap();
+        for (key, write) in writes {
+            debounced.pending.entry(key).or_insert(write);
+        }
+    }
+
+    /// Stores a key-value pair together with a content type describing the value.
+    ///
+    /// The content type is encrypted along with the value and returned on reads as
+    /// `VssItem::content_type`. Writes that don't take a content type, like `store` or
+    /// `append`, clear it.
+    ///
+    /// # Parameters
+    /// - `key`: The unique key identifier
+    /// - `value`: The binary data to store
+    /// - `content_type`: How to interpret the value, e.g. "application/json"
+    ///
+    /// # Returns
+    /// VssItem with the stored data and assigned version
+    pub async fn store_with_metadata(
+        &self,
+        key: String,
+        value: Vec<u8>,
+        content_type: Option<String>,
+    ) -> Result<VssItem, VssError> {
+        self.validate_write_key(&key)?;
+        if let Some(ref content_type) = content_type {
+            validate_content_type(content_type)?;
+        }
+
+        let version = -1;
+        let encrypted_value =
+            self.encode_value_with_content_type(value.clone(), content_type.as_deref(), version);
+
+        let request = PutObjectRequest {
+            store_id: self.store_id.clone(),
+            global_version: None,
+            transaction_items: vec![ExternalKeyValue {
+                key: self.build_key(&key),
+                version,
+                value: encrypted_value,
+            }],
+            delete_items: vec![],
+        };
+
+        match self.inner.put_object(&request).await {
+            Ok(_response) => {
+                self.remember_writes(&request);
+                Ok(VssItem {
+                    key: key.clone(),
+                    value,
+                    version: -1,
+                    last_modified_ms: None,
+                    content_type,
+                })
+            }
+            Err(e) => Err(convert_error(e, "store")),
+        }
+    }
+
+    /// Stores a key-value pair, reporting whether the key was created or updated.
+    ///
+    /// The value is first written create-only, which succeeds exactly when the key didn't
+    /// exist. If it did, the value is then stored unconditionally like `store`, so updates
+    /// cost an extra round trip. A key deleted between the two writes is reported as
+    /// updated.
+    ///
+    /// # Parameters
+    /// - `key`: The unique key identifier
+    /// - `value`: The binary data to store
+    ///
+    /// # Returns
+    /// StoreOutcome with the stored item and whether the key was created
+    pub async fn store_detailed(&self, key: String, value: Vec<u8>) -> Result<StoreOutcome, VssError> {
+        self.validate_write_key(&key)?;
+
+        match self.put_at_version(&key, value.clone(), 0).await {
+            Ok(()) => Ok(StoreOutcome {
+                item: VssItem {
+                    key,
+                    value,
+                    version: 1,
+                    last_modified_ms: None,
+                    content_type: None,
+                },
+                created: true,
+            }),
+            Err(VssError::ConflictError { .. }) => {
+                Ok(StoreOutcome { item: self.store(key, value).await?, created: false })
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Stores a key-value pair only if the key doesn't exist yet.
+    ///
+    /// Writes with version 0, which the server only accepts for a new key, so of several
+    /// devices racing to initialize the same key exactly one succeeds.
+    ///
+    /// # Parameters
+    /// - `key`: The unique key identifier
+    /// - `value`: The binary data to store
+    ///
+    /// # Returns
+    /// true if the key was created, false if it already existed
+    pub async fn store_if_absent(&self, key: String, value: Vec<u8>) -> Result<bool, VssError> {
+        self.validate_write_key(&key)?;
+
+        match self.put_at_version(&key, value, 0).await {
+            Ok(()) => Ok(true),
+            Err(VssError::ConflictError { .. }) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Appends bytes to the value of a key, creating it if it doesn't exist.
+    ///
+    /// This is a client-side read-modify-write: the current value is read, `data` is
+    /// appended, and the result is written conditionally on the version that was read.
+    /// If another writer changed the key in between, the whole cycle is retried once
+    /// before the `ConflictError` is returned.
+    ///
+    /// # Parameters
+    /// - `key`: The key to append to
+    /// - `data`: The bytes to append
+    ///
+    /// # Returns
+    /// VssItem with the full new value and its version
+    pub async fn append(&self, key: String, data: Vec<u8>) -> Result<VssItem, VssError> {
+        self.update_with_attempts(key, 2, |current| {
+            let mut value = current.unwrap_or_default();
+            value.extend_from_slice(&data);
+            value
+        })
+        .await
+    }
+
+    /// Replaces the value of a key with one computed from its current value.
+    ///
+    /// A client-side read-modify-write with optimistic concurrency: the current value is
+    /// read, passed to `updater`, and the result written conditionally on the version that
+    /// was read. If another writer changed the key in between, the cycle is repeated, up
+    /// to `MAX_UPDATE_ATTEMPTS` times in total, before the `ConflictError` is returned.
+    /// `updater` runs once per attempt.
+    ///
+    /// # Parameters
+    /// - `key`: The key to update
+    /// - `updater`: Computes the new value from the current one, None if the key doesn't exist
+    ///
+    /// # Returns
+    /// VssItem with the new value and its version
+    pub async fn update<F>(&self, key: String, updater: F) -> Result<VssItem, VssError>
+    where
+        F: Fn(Option<Vec<u8>>) -> Vec<u8> + Send + Sync,
+    {
+        self.update_with_attempts(key, MAX_UPDATE_ATTEMPTS, updater).await
+    }
+
+    /// Runs the read-modify-write cycle of `update`, giving up after `max_attempts` conflicts
+    async fn update_with_attempts<F>(
+        &self,
+        key: String,
+        max_attempts: u32,
+        updater: F,
+    ) -> Result<VssItem, VssError>
+    where
+        F: Fn(Option<Vec<u8>>) -> Vec<u8> + Send + Sync,
+    {
+        self.validate_write_key(&key)?;
+
+        let mut attempt = 1;
+        loop {
+            let (current, version) = match self.get(key.clone()).await? {
+                Some(item) => (Some(item.value), item.version),
+                None => (None, 0),
+            };
+            let value = updater(current);
+
+            match self.put_at_version(&key, value.clone(), version).await {
+                Ok(()) => {
+                    return Ok(VssItem {
+                        key,
+                        value,
+                        version: version + 1,
+                        last_modified_ms: None,
+                        content_type: None,
+                    })
+                }
+                Err(VssError::ConflictError { .. }) if attempt < max_attempts => attempt += 1,
+                Err(e) => return Err(self.with_current_version(&key, e).await),
+            }
+        }
+    }
+
+    /// Fills in the server's current version of `key`, 0 if it doesn't exist, on a
+    /// `ConflictError` that lacks it. `vss-client` only passes on the server's message, so
+    /// the version is listed separately; it stays unknown if that fails.
+    async fn with_current_version(&self, key: &str, error: VssError) -> VssError {
+        let VssError::ConflictError { error_details, current_version: None, conflicting_keys } = error
+        else {
+            return error;
+        };
+        let storage_key = self.build_key(key);
+        let current_version = self
+            .fetch_all_key_versions(Some(storage_key.clone()))
+            .await
+            .ok()
+            .map(|versions| {
+                versions.into_iter().find(|kv| kv.key == storage_key).map_or(0, |kv| kv.version)
+            });
+        VssError::ConflictError { error_details, current_version, conflicting_keys }
+    }
+
+    /// Bumps the version of a key without changing its value.
+    ///
+    /// VSS has no metadata-only update, so the stored object is read and written back
+    /// unchanged, conditionally on the version read. The value isn't decrypted, but it is
+    /// uploaded again in full.
+    ///
+    /// # Parameters
+    /// - `key`: The key to touch
+    ///
+    /// # Returns
+    /// The new version of the key
+    pub async fn touch(&self, key: String) -> Result<i64, VssError> {
+        self.validate_write_key(&key)?;
+
+        let stored = self.get_raw(&key).await?.ok_or_else(|| VssError::GetError {
+            error_details: format!("Not found: {}", display_key(&key)),
+        })?;
+
+        let request = PutObjectRequest {
+            store_id: self.store_id.clone(),
+            global_version: None,
+            transaction_items: vec![ExternalKeyValue {
+                key: stored.key,
+                version: stored.version,
+                value: stored.value,
+            }],
+            delete_items: vec![],
+        };
+
+        if let Err(e) = self.inner.put_object(&request).await {
+            return Err(self.with_current_version(&key, convert_error(e, "touch")).await);
+        }
+        self.remember_writes(&request);
+
+        Ok(stored.version + 1)
+    }
+
+    /// Sets a key to `new_value` only if its current value is exactly `expected_value`.
+    ///
+    /// The current value is read and compared after decryption, then the new value is
+    /// written conditionally on the version that was read. If that write hits a version
+    /// conflict but the value still matches on a re-read (another writer stored the same
+    /// value), the write is retried once.
+    ///
+    /// # Parameters
+    /// - `key`: The key to swap
+    /// - `new_value`: The value to write
+    /// - `expected_value`: The value the key must hold, or None if it must not exist
+    ///
+    /// # Returns
+    /// true if the new value was written, false if the current value didn't match
+    pub async fn swap(
+        &self,
+        key: String,
+        new_value: Vec<u8>,
+        expected_value: Option<Vec<u8>>,
+    ) -> Result<bool, VssError> {
+        self.validate_write_key(&key)?;
+
+        let mut retried = false;
+        loop {
+            let current = self.get(key.clone()).await?;
+            let version = match (current, &expected_value) {
+                (None, None) => 0,
+                (Some(item), Some(expected)) if &item.value == expected => item.version,
+                _ => return Ok(false),
+            };
+
+            match self.put_at_version(&key, new_value.clone(), version).await {
+                Ok(()) => return Ok(true),
+                Err(VssError::ConflictError { .. }) if !retried => retried = true,
+                Err(VssError::ConflictError { .. }) => return Ok(false),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Moves the value of `old_key` to `new_key` in a single transaction.
+    ///
+    /// The new key is written and the old key deleted atomically, so no reader sees both or
+    /// neither. The stored envelope is moved as is, keeping any content type. The delete is
+    /// conditional on the version read, so a concurrent change to `old_key` aborts the
+    /// rename with a `ConflictError`.
+    ///
+    /// # Parameters
+    /// - `old_key`: The key to move
+    /// - `new_key`: The key to move it to
+    /// - `overwrite`: Replace `new_key` if it already exists
+    ///
+    /// # Returns
+    /// true if the key was renamed, false if `old_key` didn't exist. Fails with
+    /// `ConflictError` if `new_key` exists and `overwrite` is false.
+    pub async fn rename(
+        &self,
+        old_key: String,
+        new_key: String,
+        overwrite: bool,
+    ) -> Result<bool, VssError> {
+        self.validate_write_key(&old_key)?;
+        self.validate_write_key(&new_key)?;
+
+        let Some(stored) = self.get_raw(&old_key).await? else {
+            return Ok(false);
+        };
+        if self.build_key(&old_key) == self.build_key(&new_key) {
+            return Ok(true);
+        }
+        if !overwrite && self.key_exists(&new_key).await? {
+            return Err(VssError::ConflictError {
+                error_details: format!(
+                    "Cannot rename '{}': '{}' already exists",
+                    display_key(&old_key),
+                    display_key(&new_key)
+                ),
+                current_version: None,
+                conflicting_keys: vec![],
+            });
+        }
+
+        let request = PutObjectRequest {
+            store_id: self.store_id.clone(),
+            global_version: None,
+            transaction_items: vec![ExternalKeyValue {
+                key: self.build_key(&new_key),
+                version: if overwrite { -1 } else { 0 },
+                value: stored.value,
+            }],
+            delete_items: vec![ExternalKeyValue {
+                key: stored.key,
+                version: stored.version,
+                value: vec![],
+            }],
+        };
+
+        self.inner
+            .put_object(&request)
+            .await
+            .map_err(|e| convert_error(e, "rename"))?;
+        self.remember_writes(&request);
+
+        Ok(true)
+    }
+
+    /// Writes a single key only if the server still holds it at `version` (0 for a new key)
+    async fn put_at_version(
+        &self,
+        key: &str,
+        value: Vec<u8>,
+        version: i64,
+    ) -> Result<(), VssError> {
+        let request = PutObjectRequest {
+            store_id: self.store_id.clone(),
+            global_version: None,
+            transaction_items: vec![ExternalKeyValue {
+                key: self.build_key(key),
+                version,
+                value: self.encode_value(value, version),
+            }],
+            delete_items: vec![],
+        };
+
+        self.inner
+            .put_object(&request)
+            .await
+            .map_err(|e| convert_error(e, "put_at_version"))?;
+        self.remember_writes(&request);
+
+        Ok(())
+    }
+
+    /// Retrieves a value by key.
+    ///
+    /// # Parameters
+    /// - `key`: The key to retrieve
+    ///
+    /// # Returns
+    /// Some(VssItem) if found, None if key doesn't exist
+    pub async fn get(&self, key: String) -> Result<Option<VssItem>, VssError> {
+        self.validate_key(&key)?;
+
+        match self.get_raw(&key).await? {
+            Some(kv) => Ok(Some(self.decode_item(key, kv)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Retrieves a value by key, reusing a previously read value when it is recent enough.
+    ///
+    /// A value read by this method within the last `max_age_ms` milliseconds is returned
+    /// without contacting the server. An older one is revalidated by listing the key's
+    /// version and only downloaded again if the version changed. Unconditional writes such
+    /// as `store` reset a key's version to 1 on VSS servers, so a cached value at version 1
+    /// can't be revalidated and is always downloaded again: for keys written that way this
+    /// saves nothing over `get` once `max_age_ms` has passed. Writes and deletes made
+    /// through this client drop the cached value; changes by other clients show up after at
+    /// most `max_age_ms`. Up to `MAX_CACHED_ITEMS` values are kept, dropping the least
+    /// recently used first.
+    ///
+    /// # Parameters
+    /// - `key`: The key to retrieve
+    /// - `max_age_ms`: How old a cached value may be before it is revalidated
+    ///
+    /// # Returns
+    /// Some(VssItem) if found, None if key doesn't exist
+    pub async fn get_cached(&self, key: String, max_age_ms: u64) -> Result<Option<VssItem>, VssError> {
+        self.validate_key(&key)?;
+        let storage_key = self.build_key(&key);
+
+        let cached = self.cache.lock().unwrap().get(&storage_key);
+        if let Some(cached) = cached {
+            if cached.fetched_at.elapsed() <= Duration::from_millis(max_age_ms) {
+                return Ok(Some(cached.item));
+            }
+
+            if cached.item.version > 1 {
+                let current_version = self
+                    .fetch_all_key_versions(Some(storage_key.clone()))
+                    .await
+                    .map_err(|e| convert_error(e, "get_cached"))?
+                    .into_iter()
+                    .find(|kv| kv.key == storage_key)
+                    .map(|kv| kv.version);
+
+                match current_version {
+                    Some(version) if version == cached.item.version => {
+                        self.cache_item(storage_key, Some(&cached.item));
+                        return Ok(Some(cached.item));
+                    }
+                    None => {
+                        self.cache_item(storage_key, None);
+                        return Ok(None);
+                    }
+                    Some(_) => {}
+                }
+            }
+        }
+
+        let item = self.get(key).await?;
+        self.cache_item(storage_key, item.as_ref());
+        Ok(item)
+    }
+
+    /// Keeps a freshly read value for `get_cached`, or forgets the key if it doesn't exist
+    fn cache_item(&self, storage_key: String, item: Option<&VssItem>) {
+        let mut cache = self.cache.lock().unwrap();
+        match item {
+            Some(item) => {
+                let cached = CachedItem {
+                    item: item.clone(),
+                    fetched_at: Instant::now(),
+                };
+                cache.insert(storage_key, cached);
+            }
+            None => {
+                cache.remove(&storage_key);
+            }
+        }
+    }
+
+    /// Retrieves a value by key, falling back to `default` if the key doesn't exist.
+    ///
+    /// Nothing is written: a missing key yields an item holding `default` at version 0.
+    ///
+    /// # Parameters
+    /// - `key`: The key to retrieve
+    /// - `default`: The value to return if the key doesn't exist
+    ///
+    /// # Returns
+    /// The stored VssItem, or a version 0 VssItem holding `default`
+    pub async fn get_or_default(&self, key: String, default: Vec<u8>) -> Result<VssItem, VssError> {
+        Ok(self.get(key.clone()).await?.unwrap_or(VssItem {
+            key,
+            value: default,
+            version: 0,
+            last_modified_ms: None,
+            content_type: None,
+        }))
+    }
+
+    /// Retrieves a value by key, storing `default` first if the key doesn't exist.
+    ///
+    /// The default is written create-only, so if another writer creates the key in the
+    /// meantime their value wins and is read back instead. Fails with `ConflictError` in
+    /// the unlikely case that the key is deleted again before that read.
+    ///
+    /// # Parameters
+    /// - `key`: The key to retrieve
+    /// - `default`: The value to store if the key doesn't exist
+    ///
+    /// # Returns
+    /// The VssItem that ended up stored under the key
+    pub async fn get_or_store_default(
+        &self,
+        key: String,
+        default: Vec<u8>,
+    ) -> Result<VssItem, VssError> {
+        if let Some(item) = self.get(key.clone()).await? {
+            return Ok(item);
+        }
+
+        if self.store_if_absent(key.clone(), default.clone()).await? {
+            return Ok(VssItem {
+                key,
+                value: default,
+                version: 1,
+                last_modified_ms: None,
+                content_type: None,
+            });
+        }
+
+        self.get(key.clone()).await?.ok_or_else(|| VssError::ConflictError {
+            error_details: format!("Key '{}' was created and deleted concurrently", display_key(&key)),
+            current_version: None,
+            conflicting_keys: vec![],
+        })
+    }
+
+    /// Retrieves a value by key, waiting for this client's last write to it to be visible.
+    ///
+    /// VSS has no read consistency controls, so this tracks the last write made through
+    /// this client (and its clones and views) to each key, and re-reads with a growing
+    /// delay until the server reflects that write or a later one. After
+    /// `CONSISTENT_READ_ATTEMPTS` reads it returns what the server last returned. Keys this
+    /// client hasn't written are read once, like `get`, as are keys whose write was
+    /// forgotten because more than `MAX_TRACKED_WRITES` other keys were written since.
+    ///
+    /// # Parameters
+    /// - `key`: The key to retrieve
+    ///
+    /// # Returns
+    /// Some(VssItem) if found, None if key doesn't exist
+    pub async fn get_consistent(&self, key: String) -> Result<Option<VssItem>, VssError> {
+        self.validate_key(&key)?;
+
+        let marker = self.last_writes.lock().unwrap().get(&self.build_key(&key));
+        let mut stored = self.get_raw(&key).await?;
+        if let Some(marker) = marker {
+            for attempt in 1..CONSISTENT_READ_ATTEMPTS {
+                if marker.is_reflected_by(stored.as_ref()) {
+                    break;
+                }
+                tokio::time::sleep(CONSISTENT_READ_DELAY * attempt).await;
+                stored = self.get_raw(&key).await?;
+            }
+        }
+
+        match stored {
+            Some(kv) => Ok(Some(self.decode_item(key, kv)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Decrypts a stored object into the item for a user key
+    fn decode_item(&self, key: String, kv: ExternalKeyValue) -> Result<VssItem, VssError> {
+        let (value, content_type) = self.decode_value_with_content_type(&key, &kv.value)?;
+
+        Ok(VssItem {
+            key,
+            value,
+            version: kv.version,
+            last_modified_ms: None,
+            content_type,
+        })
+    }
+
+    /// Records the writes and deletes of a successful request for `get_consistent`, and
+    /// drops the values `get_cached` holds for those keys
+    fn remember_writes(&self, request: &PutObjectRequest) {
+        let mut last_writes = self.last_writes.lock().unwrap();
+        let mut cache = self.cache.lock().unwrap();
+        for item in &request.transaction_items {
+            let marker = WriteMarker::Stored {
+                digest: sha256::Hash::hash(&item.value).to_byte_array(),
+                version: (item.version != -1).then_some(item.version + 1),
+            };
+            last_writes.insert(item.key.clone(), marker);
+            cache.remove(&item.key);
+        }
+        for item in &request.delete_items {
+            last_writes.insert(item.key.clone(), WriteMarker::Deleted);
+            cache.remove(&item.key);
+        }
+    }
+
+    /// Records the deletion of a single storage key like `remember_writes`
+    fn remember_delete(&self, storage_key: String) {
+        self.cache.lock().unwrap().remove(&storage_key);
+        self.last_writes.lock().unwrap().insert(storage_key, WriteMarker::Deleted);
+    }
+
+    /// Stores a large value in chunks, so only one chunk is encrypted and sent at a time.
+    ///
+    /// The value is split into `LARGE_VALUE_CHUNK_BYTES` chunks stored under
+    /// `<key>/chunk/<generation>/<n>`, counting from 0, each encrypted on its own. The
+    /// generation is a random identifier picked for this write, so its chunks never
+    /// overwrite those of the current value. Once all chunks are written, a manifest with
+    /// the generation, chunk count, length and SHA-256 of the value is stored at `key`
+    /// itself, with `LARGE_VALUE_MANIFEST_CONTENT_TYPE`, and the previous value's chunks are
+    /// deleted. Keys under `<key>/chunk/` are reserved for the chunks.
+    ///
+    /// A failure before the manifest is written leaves the previous value readable; the
+    /// chunks written so far are deleted on a best-effort basis. A failure while deleting
+    /// the previous chunks leaves them behind without affecting the new value.
+    ///
+    /// # Parameters
+    /// - `key`: The key to store the manifest under
+    /// - `value`: The binary data to store
+    ///
+    /// # Returns
+    /// The stored manifest item
+    pub async fn store_large(&self, key: String, value: Vec<u8>) -> Result<VssItem, VssError> {
+        self.validate_write_key(&key)?;
+        let previous = match self.get(key.clone()).await? {
+            Some(item) => self.large_value_manifest(&item)?,
+            None => None,
+        };
+
+        let mut nonce = [0u8; 8];
+        rand::thread_rng().fill_bytes(&mut nonce);
+        let generation = Some(nonce.to_lower_hex_string());
+
+        let mut chunks = 0;
+        let mut written = Ok(());
+        for chunk in value.chunks(LARGE_VALUE_CHUNK_BYTES) {
+            let chunk_key = large_value_chunk_key(&key, generation.as_deref(), chunks);
+            written = self.store(chunk_key, chunk.to_vec()).await.map(|_| ());
+            if written.is_err() {
+                break;
+            }
+            chunks += 1;
+        }
+
+        let manifest = LargeValueManifest {
+            generation: generation.clone(),
+            chunks,
+            total_len: value.len() as u64,
+            sha256: sha256::Hash::hash(&value).to_string(),
+        };
+        let manifest = serde_json::to_vec(&manifest).map_err(|e| VssError::InvalidData {
+            error_details: e.to_string(),
+        })?;
+        let item = match written {
+            Ok(()) => {
+                let content_type = Some(LARGE_VALUE_MANIFEST_CONTENT_TYPE.to_string());
+                self.store_with_metadata(key.clone(), manifest, content_type).await
+            }
+            Err(e) => Err(e),
+        };
+        let item = match item {
+            Ok(item) => item,
+            Err(e) => {
+                let _ = self.delete_large_value_chunks(&key, generation.as_deref(), chunks).await;
+                return Err(e);
+            }
+        };
+
+        if let Some(previous) = previous {
+            self.delete_large_value_chunks(&key, previous.generation.as_deref(), previous.chunks)
+                .await?;
+        }
+
+        Ok(item)
+    }
+
+    /// Deletes the chunks of one generation of a chunked value
+    async fn delete_large_value_chunks(
+        &self,
+        key: &str,
+        generation: Option<&str>,
+        chunks: u32,
+    ) -> Result<(), VssError> {
+        let items = (0..chunks)
+            .map(|index| ExternalKeyValue {
+                key: self.build_key(&large_value_chunk_key(key, generation, index)),
+                version: -1,
+                value: vec![],
+            })
+            .collect();
+        for batch in chunk_items(items, self.config.max_batch_items as usize, 0) {
+            self.delete_items(batch)
+                .await
+                .map_err(|e| convert_error(e, "store_large"))?;
+        }
+        Ok(())
+    }
+
+    /// Retrieves a value stored with `store_large`, reassembling it from its chunks.
+    ///
+    /// Values stored at `key` without chunking are returned as they are. The reassembled
+    /// value is checked against the manifest's length and hash, failing with
+    /// `VssError::IntegrityError` when a chunk is missing or belongs to another write.
+    ///
+    /// # Parameters
+    /// - `key`: The key the manifest is stored under
+    ///
+    /// # Returns
+    /// Some(VssItem) with the whole value and the manifest's version, None if key doesn't exist
+    pub async fn get_large(&self, key: String) -> Result<Option<VssItem>, VssError> {
+        let Some(item) = self.get(key.clone()).await? else {
+            return Ok(None);
+        };
+        let Some(manifest) = self.large_value_manifest(&item)? else {
+            return Ok(Some(item));
+        };
+
+        let mut value = Vec::with_capacity(manifest.total_len as usize);
+        for index in 0..manifest.chunks {
+            let chunk_key = large_value_chunk_key(&key, manifest.generation.as_deref(), index);
+            match self.get(chunk_key).await? {
+                Some(chunk) => value.extend(chunk.value),
+                None => return Err(VssError::IntegrityError { key }),
+            }
+        }
+        if value.len() as u64 != manifest.total_len
+            || sha256::Hash::hash(&value).to_string() != manifest.sha256
+        {
+            return Err(VssError::IntegrityError { key });
+        }
+
+        Ok(Some(VssItem { value, content_type: None, ..item }))
+    }
+
+    /// Parses the manifest of a chunked value, or None if the item isn't one
+    fn large_value_manifest(&self, item: &VssItem) -> Result<Option<LargeValueManifest>, VssError> {
+        if item.content_type.as_deref() != Some(LARGE_VALUE_MANIFEST_CONTENT_TYPE) {
+            return Ok(None);
+        }
+        serde_json::from_slice(&item.value)
+            .map(Some)
+            .map_err(|_| VssError::DecodeError { key: item.key.clone() })
+    }
+
+    /// Retrieves up to `length` bytes of a value starting at `offset`.
+    ///
+    /// VSS has no range reads, and every value is stored as a whole encrypted `Storable`
+    /// envelope (also without a seed, under a fixed key), so a partial ciphertext could
+    /// never be decrypted anyway. The full value is fetched and decrypted, then sliced.
+    /// This saves memory on the host side of the FFI, not bandwidth.
+    ///
+    /// # Parameters
+    /// - `key`: The key to read
+    /// - `offset`: Index of the first byte to return
+    /// - `length`: Maximum number of bytes to return
+    ///
+    /// # Returns
+    /// The requested bytes, fewer if the value ends first (empty past its end), or None if
+    /// the key doesn't exist
+    pub async fn get_range(
+        &self,
+        key: String,
+        offset: u64,
+        length: u64,
+    ) -> Result<Option<Vec<u8>>, VssError> {
+        let Some(item) = self.get(key).await? else {
+            return Ok(None);
+        };
+
+        let len = item.value.len() as u64;
+        let start = offset.min(len) as usize;
+        let end = offset.saturating_add(length).min(len) as usize;
+        Ok(Some(item.value[start..end].to_vec()))
+    }
+
+    /// Retrieves a key only if it is currently at the given version.
+    ///
+    /// VSS servers keep only the latest version of each key, so older versions cannot be
+    /// fetched. Requesting any version other than the current one fails with
+    /// `VssError::Unsupported` rather than returning the latest value.
+    ///
+    /// # Parameters
+    /// - `key`: The key to retrieve
+    /// - `version`: The version to read
+    ///
+    /// # Returns
+    /// Some(VssItem) if the key is at `version`, None if key doesn't exist
+    pub async fn get_version_at(
+        &self,
+        key: String,
+        version: i64,
+    ) -> Result<Option<VssItem>, VssError> {
+        match self.get(key).await? {
+            Some(item) if item.version == version => Ok(Some(item)),
+            Some(item) => Err(VssError::Unsupported {
+                feature: format!(
+                    "Reading historical versions (requested version {}, current version is {})",
+                    version, item.version
+                ),
+            }),
+            None => Ok(None),
+        }
+    }
+
+    /// Waits until a key is at `min_version` or later, then retrieves it.
+    ///
+    /// The version is polled with a listing scoped to the key, starting `WAIT_POLL_DELAY`
+    /// apart and doubling up to `WAIT_POLL_MAX_DELAY`. Only the final read fetches the value.
+    /// Servers reset a key to version 1 when it is overwritten unconditionally, so the
+    /// writer should use versioned writes (`put_conditional`, `update`) for versions to grow.
+    ///
+    /// # Parameters
+    /// - `key`: The key to wait for
+    /// - `min_version`: The lowest version to accept
+    /// - `timeout_ms`: How long to wait, in milliseconds. 0 checks once
+    ///
+    /// # Returns
+    /// Some(VssItem) once the key reaches `min_version`, None if the timeout elapses first
+    pub async fn wait_for_version(
+        &self,
+        key: String,
+        min_version: i64,
+        timeout_ms: u64,
+    ) -> Result<Option<VssItem>, VssError> {
+        self.validate_key(&key)?;
+
+        let deadline = Instant::now() + Duration::from_millis(timeout_ms);
+        let mut delay = WAIT_POLL_DELAY;
+        loop {
+            let version = self.key_version(&key, "wait for version").await?;
+            if version.is_some_and(|version| version >= min_version) {
+                // The key may have been deleted or reset since it was listed
+                match self.get(key.clone()).await? {
+                    Some(item) if item.version >= min_version => return Ok(Some(item)),
+                    _ => {}
+                }
+            }
+
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Ok(None);
+            }
+            tokio::time::sleep(delay.min(remaining)).await;
+            delay = (delay * 2).min(WAIT_POLL_MAX_DELAY);
+        }
+    }
+
+    /// Checks which of the given keys exist, without fetching any values.
+    ///
+    /// Each key is looked up with its own listing request scoped to the exact storage key,
+    /// which works with obfuscated keys too. Up to `MAX_CONCURRENT_GETS` lookups run at once.
+    ///
+    /// # Parameters
+    /// - `keys`: The keys to check
+    ///
+    /// # Returns
+    /// Presence flags in the same order as `keys`
+    pub async fn exists_many(&self, keys: Vec<String>) -> Result<Vec<bool>, VssError> {
+        for key in &keys {
+            self.validate_key(key)?;
+        }
+
+        let mut tasks = tokio::task::JoinSet::new();
+        let mut exists = vec![false; keys.len()];
+        let mut checked = Vec::with_capacity(keys.len());
+
+        for (index, key) in keys.into_iter().enumerate() {
+            if tasks.len() >= MAX_CONCURRENT_GETS {
+                if let Some(joined) = tasks.join_next().await {
+                    checked.push(joined_exists(joined)?);
+                }
+            }
+
+            let client = self.clone();
+            tasks.spawn(async move { (index, client.key_exists(&key).await) });
+        }
+        while let Some(joined) = tasks.join_next().await {
+            checked.push(joined_exists(joined)?);
+        }
+
+        for (index, found) in checked {
+            exists[index] = found;
+        }
+        Ok(exists)
+    }
+
+    /// Looks up a single key with a listing scoped to its exact storage key
+    async fn key_exists(&self, key: &str) -> Result<bool, VssError> {
+        Ok(self.key_version(key, "exists").await?.is_some())
+    }
+
+    /// Returns the current version of a single key without fetching its value
+    async fn key_version(&self, key: &str, operation: &str) -> Result<Option<i64>, VssError> {
+        let storage_key = self.build_key(key);
+        let request = ListKeyVersionsRequest {
+            store_id: self.store_id.clone(),
+            key_prefix: Some(storage_key.clone()),
+            page_size: Some(1),
+            page_token: None,
+        };
+
+        let response = self
+            .inner
+            .list_key_versions(&request)
+            .await
+            .map_err(|e| convert_error(e, operation))?;
+
+        // The exact key sorts before any longer key sharing it as a prefix
+        Ok(response.key_versions.iter().find(|kv| kv.key == storage_key).map(|kv| kv.version))
+    }
+
+    /// Returns the size of a stored value as held by the server (after encryption).
+    ///
+    /// # Parameters
+    /// - `key`: The key to inspect
+    ///
+    /// # Returns
+    /// Some(size in bytes) if found, None if key doesn't exist
+    pub async fn item_size(&self, key: String) -> Result<Option<u64>, VssError> {
+        self.validate_key(&key)?;
+
+        Ok(self.get_raw(&key).await?.map(|kv| kv.value.len() as u64))
+    }
+
+    /// Computes the key count, total stored size and highest version of the keys under a
+    /// prefix.
+    ///
+    /// Keys and versions come from paging through the key list; sizes need a raw get per
+    /// key, run `MAX_CONCURRENT_GETS` at a time. Values are not decrypted. Keys deleted
+    /// while this runs count with a size of 0.
+    ///
+    /// # Parameters
+    /// - `prefix`: Optional key prefix filter
+    ///
+    /// # Returns
+    /// The StoreStats for the matching keys
+    pub async fn store_stats(&self, prefix: Option<String>) -> Result<StoreStats, VssError> {
+        let key_versions = self
+            .fetch_all_key_versions(self.server_key_prefix(prefix.as_deref()))
+            .await
+            .map_err(|e| convert_error(e, "store_stats"))?;
+
+        let mut stats = StoreStats { key_count: 0, total_bytes: 0, max_version: 0 };
+        let mut tasks = tokio::task::JoinSet::new();
+        for kv in key_versions {
+            if self.extract_key_with_prefix(&kv.key, prefix.as_deref())?.is_none() {
+                continue;
+            }
+
+            stats.key_count += 1;
+            stats.max_version = stats.max_version.max(kv.version);
+
+            if tasks.len() >= MAX_CONCURRENT_GETS {
+                if let Some(joined) = tasks.join_next().await {
+                    stats.total_bytes += joined_task(joined)?;
+                }
+            }
+            let client = self.clone();
+            tasks.spawn(async move { client.stored_size(kv.key).await });
+        }
+        while let Some(joined) = tasks.join_next().await {
+            stats.total_bytes += joined_task(joined)?;
+        }
+
+        Ok(stats)
+    }
+
+    /// Fetches the stored size of a storage key, 0 if it no longer exists
+    async fn stored_size(&self, storage_key: String) -> Result<u64, VssError> {
+        let request = GetObjectRequest {
+            store_id: self.store_id.clone(),
+            key: storage_key,
+        };
+
+        match self.inner.get_object(&request).await {
+            Ok(response) => Ok(response.value.map_or(0, |kv| kv.value.len() as u64)),
+            Err(BackendError { error: ExternalVssError::NoSuchKeyError(_), .. }) => Ok(0),
+            Err(e) => Err(convert_error(e, "store_stats")),
+        }
+    }
+
+    /// Fetches the stored object for a user key without decoding or decrypting it
+    pub(crate) async fn get_raw(&self, key: &str) -> Result<Option<ExternalKeyValue>, VssError> {
+        let request = GetObjectRequest {
+            store_id: self.store_id.clone(),
+            key: self.build_key(key),
+        };
+
+        match self.inner.get_object(&request).await {
+            Ok(response) => Ok(response.value),
+            Err(BackendError { error: ExternalVssError::NoSuchKeyError(_), .. }) => Ok(None),
+            Err(e) => Err(convert_error(e, "get")),
+        }
+    }
+
+    /// Lists all items, optionally filtered by key prefix.
+    ///
+    /// With obfuscated keys the server can't filter by prefix, so every page of the key
+    /// list is fetched and filtered by user key; see `resolve_storage_prefix`. Values are
+    /// then fetched concurrently. If any value can't be fetched or decoded the whole
+    /// listing fails; `list_partial` reports such keys instead. Keys deleted between the
+    /// listing and the fetch are left out.
+    ///
+    /// # Parameters
+    /// - `prefix`: Optional key prefix filter
+    ///
+    /// # Returns
+    /// Vector of all matching VssItems with their data, in key order like `list_keys`
+    pub async fn list(&self, prefix: Option<String>) -> Result<Vec<VssItem>, VssError> {
+        let keys = self.list_key_names_for(prefix.as_deref(), "list").await?;
+        self.fetch_items(keys).await
+    }
+
+    /// Lists all items like `list`, collecting the keys whose values can't be fetched or
+    /// decoded instead of failing on them.
+    ///
+    /// # Parameters
+    /// - `prefix`: Optional key prefix filter
+    ///
+    /// # Returns
+    /// The items that were read, in key order, and the keys that failed with their errors
+    pub async fn list_partial(&self, prefix: Option<String>) -> Result<BatchGetResult, VssError> {
+        let keys = self.list_key_names_for(prefix.as_deref(), "list_partial").await?;
+        self.get_many_partial(keys, None).await
+    }
+
+    /// Retrieves all items under a prefix as a map from key to value.
+    ///
+    /// Keys are listed like `list` and the values fetched concurrently. Versions and
+    /// content types are dropped; use `list` when they matter. Like `list`, fails if any
+    /// value can't be read.
+    ///
+    /// # Parameters
+    /// - `prefix`: Optional key prefix filter
+    ///
+    /// # Returns
+    /// The values of all matching keys, keyed by user key
+    pub async fn get_map(&self, prefix: Option<String>) -> Result<HashMap<String, Vec<u8>>, VssError> {
+        let keys = self.list_key_names_for(prefix.as_deref(), "get_map").await?;
+        let items = self.fetch_items(keys).await?;
+        Ok(items.into_iter().map(|item| (item.key, item.value)).collect())
+    }
+
+    /// Lists the user keys under a prefix, sorted, failing with `operation` named in the
+    /// error
+    async fn list_key_names_for(
+        &self,
+        prefix: Option<&str>,
+        operation: &str,
+    ) -> Result<Vec<String>, VssError> {
+        let key_versions = self
+            .fetch_all_key_versions(self.server_key_prefix(prefix))
+            .await
+            .map_err(|e| convert_error(e, operation))?;
+
+        let mut keys = Vec::new();
+        for key_version in key_versions {
+            keys.extend(self.extract_key_with_prefix(&key_version.key, prefix)?);
+        }
+        keys.sort();
+        Ok(keys)
+    }
+
+    /// Lists at most `limit` items, stopping as soon as that many have been fetched.
+    ///
+    /// Keys are listed page by page, each page no larger than the number of items still
+    /// needed, and only the values of listed keys are fetched, concurrently.
+    ///
+    /// Which items are returned follows the server's listing order. With obfuscated keys
+    /// that order is unrelated to the user keys, so the items are not the first `limit`
+    /// keys but an unspecified selection of them. Only the returned items are sorted.
+    ///
+    /// # Parameters
+    /// - `prefix`: Optional key prefix filter
+    /// - `limit`: Maximum number of items to return
+    ///
+    /// # Returns
+    /// Up to `limit` matching VssItems, sorted by key
+    pub async fn list_limited(
+        &self,
+        prefix: Option<String>,
+        limit: u32,
+    ) -> Result<Vec<VssItem>, VssError> {
+        let mut items = Vec::new();
+        let mut page_token = None;
+
+        while items.len() < limit as usize {
+            let remaining = limit - items.len() as u32;
+            let page = self
+                .key_versions_page(
+                    prefix.clone(),
+                    Some(remaining.min(self.page_size())),
+                    page_token,
+                    "list_limited",
+                )
+                .await?;
+            let keys = page.key_versions.into_iter().map(|kv| kv.key).collect();
+            items.extend(self.fetch_items(keys).await?);
+
+            match page.next_page_token {
+                Some(token) => page_token = Some(token),
+                None => break,
+            }
+        }
+
+        items.truncate(limit as usize);
+        items.sort_by(|a, b| a.key.cmp(&b.key));
+        Ok(items)
+    }
+
+    /// Lists one page of items, fetching values only for the keys on that page.
+    ///
+    /// With obfuscated keys the prefix is applied to each page on the client, so a page can
+    /// hold fewer than `page_size` keys, or none, while more pages follow.
+    ///
+    /// # Parameters
+    /// - `prefix`: Optional key prefix filter
+    /// - `page_size`: Optional maximum number of keys per page (server default if None)
+    /// - `page_token`: Token from the previous page, None for the first page
+    ///
+    /// # Returns
+    /// ListPage with the page's items and the token for the next page
+    pub async fn list_page(
+        &self,
+        prefix: Option<String>,
+        page_size: Option<u32>,
+        page_token: Option<String>,
+    ) -> Result<ListPage, VssError> {
+        let page = self
+            .key_versions_page(prefix, page_size, page_token, "list_page")
+            .await?;
+        let keys = page.key_versions.into_iter().map(|kv| kv.key).collect();
+
+        Ok(ListPage {
+            items: self.fetch_items(keys).await?,
+            next_page_token: page.next_page_token,
+        })
+    }
+
+    /// Lists one page of keys and versions without retrieving values.
+    ///
+    /// With obfuscated keys the prefix is applied to each page on the client, so a page can
+    /// hold fewer than `page_size` keys, or none, while more pages follow.
+    ///
+    /// # Parameters
+    /// - `prefix`: Optional key prefix filter
+    /// - `page_size`: Optional maximum number of keys per page (server default if None)
+    /// - `page_token`: Token from the previous page, None for the first page
+    ///
+    /// # Returns
+    /// ListKeyVersionsResponse with the page's keys and the token for the next page
+    pub async fn list_keys_page(
+        &self,
+        prefix: Option<String>,
+        page_size: Option<u32>,
+        page_token: Option<String>,
+    ) -> Result<ListKeyVersionsResponse, VssError> {
+        self.key_versions_page(prefix, page_size, page_token, "list_keys_page")
+            .await
+    }
+
+    /// Fetches one page of user keys from the server, with the token for the next page
+    async fn key_versions_page(
+        &self,
+        prefix: Option<String>,
+        page_size: Option<u32>,
+        page_token: Option<String>,
+        operation: &str,
+    ) -> Result<ListKeyVersionsResponse, VssError> {
+        let request = ListKeyVersionsRequest {
+            store_id: self.store_id.clone(),
+            key_prefix: self.server_key_prefix(prefix.as_deref()),
+            page_size: page_size.map(|size| size.min(i32::MAX as u32) as i32),
+            page_token,
+        };
+
+        let response = self
+            .inner
+            .list_key_versions(&request)
+            .await
+            .map_err(|e| convert_error(e, operation))?;
+
+        let mut key_versions = Vec::new();
+        for kv in &response.key_versions {
+            if let Some(original_key) = self.extract_key_with_prefix(&kv.key, prefix.as_deref())? {
+                key_versions.push(KeyVersion {
+                    key: original_key,
+                    version: kv.version,
+                    last_modified_ms: None,
+                });
+            }
+        }
+
+        let next_page_token = if response.key_versions.is_empty() {
+            None
+        } else {
+            response.next_page_token.filter(|token| !token.is_empty())
+        };
+
+        Ok(ListKeyVersionsResponse {
+            key_versions,
+            next_page_token,
+        })
+    }
+
+    /// Fetches the given keys concurrently, preserving their order and skipping
+    /// keys that no longer exist
+    async fn fetch_items(&self, keys: Vec<String>) -> Result<Vec<VssItem>, VssError> {
+        let mut items = Vec::with_capacity(keys.len());
+        for (_, result) in self.fetch_results(keys, None, None).await? {
+            items.extend(result?);
+        }
+        Ok(items)
+    }
+
+    /// Fetches the given keys concurrently, returning each key's own result in input order.
+    /// Cancelling `cancel` stops spawning lookups and aborts the ones in flight. `progress` is
+    /// told each time a lookup completes.
+    async fn fetch_results(
+        &self,
+        keys: Vec<String>,
+        cancel: Option<Arc<VssCancelHandle>>,
+        progress: Option<&dyn VssProgress>,
+    ) -> Result<Vec<(String, FetchResult)>, VssError> {
+        let mut tasks = tokio::task::JoinSet::new();
+        let total = keys.len() as u64;
+        let mut fetched = Vec::with_capacity(keys.len());
+        let report = |done: usize| {
+            if let Some(progress) = progress {
+                progress.on_progress(done as u64, total);
+            }
+        };
+
+        for (index, key) in keys.into_iter().enumerate() {
+            check_cancelled(cancel.as_deref())?;
+            if tasks.len() >= MAX_CONCURRENT_GETS {
+                if let Some(joined) = tasks.join_next().await {
+                    fetched.push(joined_result(joined)?);
+                    report(fetched.len());
+                }
+            }
+
+            let client = self.clone();
+            tasks.spawn(async move {
+                let result = client.get(key.clone()).await;
+                (index, key, result)
+            });
+        }
+        while let Some(joined) = tasks.join_next().await {
+            check_cancelled(cancel.as_deref())?;
+            fetched.push(joined_result(joined)?);
+            report(fetched.len());
+        }
+
+        fetched.sort_by_key(|(index, _, _)| *index);
+        Ok(fetched.into_iter().map(|(_, key, result)| (key, result)).collect())
+    }
+
+    /// Retrieves several keys, collecting per-key failures instead of aborting on them.
+    ///
+    /// Keys that don't exist appear in neither list. Reads have no side effects, so
+    /// cancelling discards whatever was fetched so far.
+    ///
+    /// # Parameters
+    /// - `keys`: The keys to retrieve
+    /// - `cancel`: Optional handle that stops the batch with `VssError::Cancelled` once cancelled
+    ///
+    /// # Returns
+    /// The items that were read, in input order, and the keys that failed with their errors
+    pub async fn get_many_partial(
+        &self,
+        keys: Vec<String>,
+        cancel: Option<Arc<VssCancelHandle>>,
+    ) -> Result<BatchGetResult, VssError> {
+        let mut items = Vec::new();
+        let mut failures = Vec::new();
+
+        for (key, result) in self.fetch_results(keys, cancel, None).await? {
+            match result {
+                Ok(item) => items.extend(item),
+                Err(e) => failures.push(KeyError { key, error: e.to_string() }),
+            }
+        }
+
+        Ok(BatchGetResult { items, failures })
+    }
+
+    /// Lists keys and versions without retrieving values.
+    ///
+    /// Pages through the full key list. With obfuscated keys the server can't filter by
+    /// prefix, so the keys are filtered by user key; see `resolve_storage_prefix`.
+    ///
+    /// The server pages keys in storage order, which for obfuscated keys bears no relation
+    /// to the user keys, so the result is sorted by user key, comparing UTF-8 bytes. Two
+    /// devices listing the same store get the same order.
+    ///
+    /// # Parameters
+    /// - `prefix`: Optional key prefix filter
+    ///
+    /// # Returns
+    /// Vector of KeyVersion structs sorted by key (more efficient than list())
+    pub async fn list_keys(&self, prefix: Option<String>) -> Result<Vec<KeyVersion>, VssError> {
+        let key_versions = self
+            .fetch_all_key_versions(self.server_key_prefix(prefix.as_deref()))
+            .await
+            .map_err(|e| convert_error(e, "list_keys"))?;
+
+        let mut result = Vec::new();
+        for kv in key_versions {
+            let Some(original_key) = self.extract_key_with_prefix(&kv.key, prefix.as_deref())?
+            else {
+                continue;
+            };
+
+            result.push(KeyVersion {
+                key: original_key,
+                version: kv.version,
+                last_modified_ms: None,
+            });
+        }
+        result.sort_by(|a, b| a.key.cmp(&b.key));
+        Ok(result)
+    }
+
+    /// Lists the user keys under a prefix, without versions or values.
+    ///
+    /// Makes the same paged requests as `list_keys`: the server always reports versions,
+    /// so this only saves building the results. Prefix filtering works like `list_keys`.
     ///
     /// # Parameters
     /// - `prefix`: Optional key prefix filter
     ///
     /// # Returns
-    /// Vector of all matching VssItems with their data
-    pub async fn list(&self, prefix: Option<String>) -> Result<Vec<VssItem>, VssError> {
-        let request = ListKeyVersionsRequest {
+    /// The user keys, sorted like `list_keys`
+    pub async fn list_key_names(&self, prefix: Option<String>) -> Result<Vec<String>, VssError> {
+        self.list_key_names_for(prefix.as_deref(), "list_key_names").await
+    }
+
+    /// Compares this store with another one by decrypted value, e.g. to check a copied
+    /// store before deleting the original.
+    ///
+    /// Both key lists are fetched in full. The values of the keys both stores hold are then
+    /// compared in batches of `DIFF_BATCH_KEYS` keys, in key order, fetched concurrently
+    /// within each store, so only one batch of values is held in memory at a time. Versions
+    /// and content types are not compared, as they legitimately differ between stores, and
+    /// the two clients may use different encryption keys. A key deleted from one store after
+    /// the listing counts as missing from that store.
+    ///
+    /// # Parameters
+    /// - `other`: Client for the store to compare against
+    ///
+    /// # Returns
+    /// The keys found only in this store, only in `other`, and in both with different values
+    pub async fn diff_against(&self, other: Arc<VssClient>) -> Result<StoreDiff, VssError> {
+        let own_keys = self.list_key_names_for(None, "diff_against").await?;
+        let other_keys = other.list_key_names_for(None, "diff_against").await?;
+
+        let mut diff = StoreDiff {
+            only_in_self: Vec::new(),
+            only_in_other: Vec::new(),
+            differing: Vec::new(),
+        };
+        let mut shared = Vec::new();
+        let mut own_keys = own_keys.into_iter().peekable();
+        let mut other_keys = other_keys.into_iter().peekable();
+        loop {
+            match (own_keys.peek(), other_keys.peek()) {
+                (Some(own), Some(theirs)) if own == theirs => {
+                    shared.extend(own_keys.next());
+                    other_keys.next();
+                }
+                (Some(own), Some(theirs)) if own < theirs => {
+                    diff.only_in_self.extend(own_keys.next())
+                }
+                (Some(_), Some(_)) | (None, Some(_)) => diff.only_in_other.extend(other_keys.next()),
+                (Some(_), None) => diff.only_in_self.extend(own_keys.next()),
+                (None, None) => break,
+            }
+        }
+
+        for batch in shared.chunks(DIFF_BATCH_KEYS) {
+            let own_items = self.fetch_results(batch.to_vec(), None, None).await?;
+            let other_items = other.fetch_results(batch.to_vec(), None, None).await?;
+            for ((key, own), (_, theirs)) in own_items.into_iter().zip(other_items) {
+                match (own?, theirs?) {
+                    (Some(own), Some(theirs)) if own.value != theirs.value => diff.differing.push(key),
+                    (Some(_), None) => diff.only_in_self.push(key),
+                    (None, Some(_)) => diff.only_in_other.push(key),
+                    _ => {}
+                }
+            }
+        }
+        diff.only_in_self.sort();
+        diff.only_in_other.sort();
+        Ok(diff)
+    }
+
+    /// Stores multiple key-value pairs under a common prefix.
+    ///
+    /// Prepends `prefix` to each item's key before it is obfuscated, then writes the items
+    /// like `put_with_key_prefix`, which despite its name takes the keys as they are.
+    ///
+    /// # Parameters
+    /// - `prefix`: Prefix to prepend to every key, e.g. "config/"
+    /// - `items`: Vector of KeyValue pairs with keys relative to `prefix`
+    ///
+    /// # Returns
+    /// Vector of stored VssItems, keyed by their full prefixed keys
+    pub async fn put_all_with_prefix(
+        &self,
+        prefix: String,
+        items: Vec<KeyValue>,
+    ) -> Result<Vec<VssItem>, VssError> {
+        let items = items
+            .into_iter()
+            .map(|item| KeyValue {
+                key: format!("{}{}", prefix, item.key),
+                value: item.value,
+            })
+            .collect();
+        self.put_with_key_prefix(items).await
+    }
+
+    /// Stores multiple key-value pairs, split into transactions that fit the batch limits.
+    ///
+    /// Keys are used as given; see `put_all_with_prefix` to prepend a common prefix.
+    ///
+    /// Each chunk of at most `max_batch_items` items and `max_batch_bytes` encoded bytes
+    /// is written as its own atomic transaction, so atomicity holds per chunk only. A batch
+    /// within those limits is a single transaction: if it fails, nothing was written. If
+    /// a later chunk fails, the chunks before it stay written; use
+    /// `put_with_key_prefix_partial` to learn which.
+    ///
+    /// Batches larger in total than `max_request_bytes` fail with `VssError::InvalidData`
+    /// once the items are encoded, before any chunk is sent.
+    ///
+    /// # Parameters
+    /// - `items`: Vector of KeyValue pairs to store
+    ///
+    /// # Returns
+    /// Vector of stored VssItems with assigned versions
+    pub async fn put_with_key_prefix(
+        &self,
+        items: Vec<KeyValue>,
+    ) -> Result<Vec<VssItem>, VssError> {
+        match self.put_batched(items, None).await? {
+            (_, Some((_, error))) => Err(error),
+            (written, None) => Ok(written),
+        }
+    }
+
+    /// Stores multiple key-value pairs like `put_with_key_prefix`, reporting how far the
+    /// write got when a chunk fails instead of failing as a whole.
+    ///
+    /// Chunks are written in order and the first failed chunk stops the batch, so the
+    /// items written are exactly those of the chunks before it. Invalid keys are still
+    /// rejected with an error before anything is written.
+    ///
+    /// # Parameters
+    /// - `items`: Vector of KeyValue pairs to store
+    ///
+    /// # Returns
+    /// The written items and, if a chunk failed, its index and error
+    pub async fn put_with_key_prefix_partial(
+        &self,
+        items: Vec<KeyValue>,
+    ) -> Result<BatchPutResult, VssError> {
+        let (written, failure) = self.put_batched(items, None).await?;
+        let (failed_chunk_index, error) = match failure {
+            Some((index, error)) => (Some(index as u32), Some(error.to_string())),
+            None => (None, None),
+        };
+        Ok(BatchPutResult { written, failed_chunk_index, error })
+    }
+
+    /// Implements `put_with_key_prefix`, telling `progress` how many items were written
+    /// after each transaction. Stops at the first chunk that fails, returning the items
+    /// written before it together with the chunk's index and error.
+    async fn put_batched(
+        &self,
+        items: Vec<KeyValue>,
+        progress: Option<&dyn VssProgress>,
+    ) -> Result<(Vec<VssItem>, Option<(usize, VssError)>), VssError> {
+        self.check_writable()?;
+        for item in &items {
+            self.validate_write_key(&item.key)?;
+        }
+
+        let version = -1;
+        let external_items: Vec<ExternalKeyValue> = items
+            .iter()
+            .map(|item| ExternalKeyValue {
+                key: self.build_key(&item.key),
+                value: self.encode_value(item.value.clone(), version),
+                version,
+            })
+            .collect();
+        self.check_request_bytes(&external_items)?;
+
+        // Chunks keep the items in order, so the items written are always a prefix
+        let chunks = chunk_items(
+            external_items,
+            self.config.max_batch_items as usize,
+            self.config.max_batch_bytes as usize,
+        );
+        let total = items.len() as u64;
+        let mut written = 0;
+        let mut failure = None;
+        for (index, chunk) in chunks.into_iter().enumerate() {
+            let chunk_len = chunk.len();
+            let request = PutObjectRequest {
+                store_id: self.store_id.clone(),
+                global_version: None,
+                transaction_items: chunk,
+                delete_items: vec![],
+            };
+
+            if let Err(e) = self.inner.put_object(&request).await {
+                failure = Some((index, convert_error(e, "put_with_key_prefix")));
+                break;
+            }
+            self.remember_writes(&request);
+
+            written += chunk_len;
+            if let Some(progress) = progress {
+                progress.on_progress(written as u64, total);
+            }
+        }
+
+        let written = items
+            .into_iter()
+            .take(written)
+            .map(|item| VssItem {
+                key: item.key,
+                value: item.value,
+                version: -1,
+                last_modified_ms: None,
+                content_type: None,
+            })
+            .collect();
+        Ok((written, failure))
+    }
+
+    /// Rejects a batch whose encoded size, counted like `chunk_items` does, exceeds
+    /// `max_request_bytes`
+    fn check_request_bytes(&self, items: &[ExternalKeyValue]) -> Result<(), VssError> {
+        let Some(max_bytes) = self.config.max_request_bytes else {
+            return Ok(());
+        };
+        let total_bytes: u64 =
+            items.iter().map(|item| (item.key.len() + item.value.len()) as u64).sum();
+        if total_bytes > max_bytes {
+            return Err(VssError::InvalidData {
+                error_details: format!(
+                    "Batch of {} items is {} bytes encoded, over the max_request_bytes limit of {}",
+                    items.len(),
+                    total_bytes,
+                    max_bytes
+                ),
+            });
+        }
+        Ok(())
+    }
+
+    /// Writes several keys in one transaction, each only if it is at its expected version.
+    ///
+    /// If any key's version doesn't match, nothing is written and a `ConflictError` is
+    /// returned whose `conflicting_keys` lists the mismatched keys. The server doesn't say
+    /// which item failed, so the keys' current versions are read back to find them; a key
+    /// can be missing from the list if it was changed back in the meantime. Unlike
+    /// `put_with_key_prefix`, the batch is never split, so a batch exceeding the config's
+    /// batch limits is rejected.
+    ///
+    /// # Parameters
+    /// - `items`: The keys to write with their values and expected versions: 0 for a key
+    ///   that must not exist yet, -1 to write the key unconditionally
+    ///
+    /// # Returns
+    /// The written items with their new versions, -1 for unconditional writes
+    pub async fn put_conditional(
+        &self,
+        items: Vec<VersionedKeyValue>,
+    ) -> Result<Vec<VssItem>, VssError> {
+        self.check_writable()?;
+        for item in &items {
+            self.validate_write_key(&item.key)?;
+        }
+
+        let transaction_items: Vec<ExternalKeyValue> = items
+            .iter()
+            .map(|item| ExternalKeyValue {
+                key: self.build_key(&item.key),
+                value: self.encode_value(item.value.clone(), item.expected_version),
+                version: item.expected_version,
+            })
+            .collect();
+        self.check_request_bytes(&transaction_items)?;
+        // The limits `chunk_items` splits batches at
+        let max_items = self.config.max_batch_items as usize;
+        let max_bytes = self.config.max_batch_bytes as usize;
+        let total_bytes: usize =
+            transaction_items.iter().map(|item| item.key.len() + item.value.len()).sum();
+        if (max_items > 0 && transaction_items.len() > max_items)
+            || (max_bytes > 0 && transaction_items.len() > 1 && total_bytes > max_bytes)
+        {
+            return Err(VssError::InvalidData {
+                error_details: format!(
+                    "Conditional batch of {} items exceeds the batch limits and can't be written atomically",
+                    items.len()
+                ),
+            });
+        }
+
+        let request = PutObjectRequest {
             store_id: self.store_id.clone(),
-            key_prefix: prefix.as_ref().map(|p| self.build_key(p)),
-            page_size: None,
-            page_token: None,
+            global_version: None,
+            transaction_items,
+            delete_items: vec![],
+        };
+        match self.inner.put_object(&request).await {
+            Ok(_) => self.remember_writes(&request),
+            Err(BackendError { error: ExternalVssError::ConflictError(msg), .. }) => {
+                return Err(self.batch_conflict(&items, msg).await)
+            }
+            Err(e) => return Err(convert_error(e, "put_conditional")),
+        }
+
+        Ok(items
+            .into_iter()
+            .map(|item| VssItem {
+                key: item.key,
+                value: item.value,
+                version: if item.expected_version == -1 { -1 } else { item.expected_version + 1 },
+                last_modified_ms: None,
+                content_type: None,
+            })
+            .collect())
+    }
+
+    /// Builds the error for a conflicting `put_conditional`, reading back the keys'
+    /// versions to find the ones that didn't match
+    async fn batch_conflict(&self, items: &[VersionedKeyValue], msg: String) -> VssError {
+        let mut conflicting = Vec::new();
+        for item in items.iter().filter(|item| item.expected_version != -1) {
+            match self.get_raw(&item.key).await {
+                Ok(stored) => {
+                    let version = stored.map_or(0, |kv| kv.version);
+                    if version != item.expected_version {
+                        conflicting.push((item.key.clone(), version));
+                    }
+                }
+                Err(e) => return e,
+            }
+        }
+
+        let current_version = match conflicting.as_slice() {
+            [(_, version)] => Some(*version),
+            _ => None,
+        };
+        let keys: Vec<String> = conflicting.into_iter().map(|(key, _)| key).collect();
+        VssError::ConflictError {
+            error_details: format!(
+                "{} (conflicting keys: {})",
+                msg,
+                keys.iter().map(|key| display_key(key)).collect::<Vec<_>>().join(", ")
+            ),
+            current_version,
+            conflicting_keys: keys,
+        }
+    }
+
+    /// Deletes a key-value pair.
+    ///
+    /// # Parameters
+    /// - `key`: The key to delete
+    ///
+    /// # Returns
+    /// true if deleted, false if key didn't exist
+    pub async fn delete(&self, key: String) -> Result<bool, VssError> {
+        self.validate_write_key(&key)?;
+
+        let request = DeleteObjectRequest {
+            store_id: self.store_id.clone(),
+            key_value: Some(ExternalKeyValue {
+                key: self.build_key(&key),
+                version: -1,
+                value: vec![],
+            }),
+        };
+
+        let storage_key = self.build_key(&key);
+        match self.inner.delete_object(&request).await {
+            Ok(_) => {
+                self.remember_delete(storage_key);
+                Ok(true)
+            }
+            Err(BackendError { error: ExternalVssError::NoSuchKeyError(_), .. }) => Ok(false),
+            Err(e) => Err(convert_error(e, "delete")),
+        }
+    }
+
+    /// Deletes a key and returns the item it held, e.g. to offer an undo.
+    ///
+    /// The item is read first and then deleted in a transaction conditional on the version
+    /// read, so the returned value is exactly what was removed. If another writer deleted
+    /// or rewrote the key in between, the item read is already gone: nothing is deleted and
+    /// None is returned, leaving a rewritten key in place.
+    ///
+    /// # Parameters
+    /// - `key`: The key to delete
+    ///
+    /// # Returns
+    /// The deleted item, or None if the key didn't exist or changed before it was deleted
+    pub async fn delete_returning(&self, key: String) -> Result<Option<VssItem>, VssError> {
+        self.validate_write_key(&key)?;
+
+        let Some(item) = self.get(key.clone()).await? else {
+            return Ok(None);
+        };
+        let delete_item = ExternalKeyValue {
+            key: self.build_key(&key),
+            version: item.version,
+            value: vec![],
+        };
+
+        match self.delete_items(vec![delete_item]).await {
+            Ok(()) => Ok(Some(item)),
+            Err(BackendError { error: ExternalVssError::ConflictError(_), .. }) => Ok(None),
+            Err(e) => Err(convert_error(e, "delete_returning")),
+        }
+    }
+
+    /// Deletes every key visible to this client, paging through the full key list.
+    ///
+    /// Deletes are sent in batches of at most `max_batch_items` keys, unconditionally, so
+    /// keys written while the wipe runs may survive it. On a prefixed view only keys under
+    /// the view's prefix are deleted.
+    ///
+    /// Can partially complete when cancelled: the handle is checked between batches, and
+    /// batches already deleted stay deleted.
+    ///
+    /// # Parameters
+    /// - `confirm`: Must be `true` to delete, guarding against accidental wipes
+    /// - `dry_run`: List the keys that would be deleted without deleting anything.
+    ///   Doesn't require `confirm`.
+    /// - `cancel`: Optional handle that stops the wipe with `VssError::Cancelled` once cancelled
+    ///
+    /// # Returns
+    /// The keys that were deleted, or would be on a dry run
+    pub async fn clear_store(
+        &self,
+        confirm: bool,
+        dry_run: bool,
+        cancel: Option<Arc<VssCancelHandle>>,
+    ) -> Result<Vec<String>, VssError> {
+        if !confirm && !dry_run {
+            return Err(VssError::InvalidData {
+                error_details: "clear_store requires confirm to be true".to_string(),
+            });
+        }
+        if !dry_run {
+            self.check_writable()?;
+        }
+
+        let key_versions = self
+            .fetch_all_key_versions(self.server_key_prefix(None))
+            .await
+            .map_err(|e| convert_error(e, "clear_store"))?;
+
+        let mut keys = Vec::new();
+        let mut delete_items = Vec::new();
+        for kv in key_versions {
+            if let Some(key) = self.extract_key(&kv.key)? {
+                keys.push(key);
+                delete_items.push(ExternalKeyValue { key: kv.key, version: -1, value: vec![] });
+            }
+        }
+        if dry_run {
+            return Ok(keys);
+        }
+
+        for chunk in chunk_items(delete_items, self.config.max_batch_items as usize, 0) {
+            check_cancelled(cancel.as_deref())?;
+            let request = PutObjectRequest {
+                store_id: self.store_id.clone(),
+                global_version: None,
+                transaction_items: vec![],
+                delete_items: chunk,
+            };
+            self.inner
+                .put_object(&request)
+                .await
+                .map_err(|e| convert_error(e, "clear_store"))?;
+            self.remember_writes(&request);
+        }
+
+        Ok(keys)
+    }
+
+    /// Deletes the keys matching a pattern in which `*` stands for any run of characters,
+    /// including none and `/`, e.g. `state/device-*/channels`.
+    ///
+    /// This is a client-side scan: every key under the pattern's leading literal part is
+    /// listed and matched here, which for a pattern starting with `*`, or any pattern on an
+    /// obfuscated store, means listing the whole store. Matches are deleted unconditionally
+    /// in batches of `max_batch_items`; if a batch fails, earlier batches stay deleted.
+    /// Patterns made up of `*` only are rejected, use `clear_store` to delete everything.
+    ///
+    /// # Parameters
+    /// - `pattern`: The pattern keys must match in full
+    ///
+    /// # Returns
+    /// The keys that were deleted
+    pub async fn delete_matching(&self, pattern: String) -> Result<Vec<String>, VssError> {
+        self.check_writable()?;
+        if pattern.chars().all(|c| c == '*') {
+            return Err(VssError::InvalidData {
+                error_details: format!(
+                    "Pattern {:?} matches every key; use clear_store instead",
+                    pattern
+                ),
+            });
+        }
+
+        let literal_prefix = pattern.split('*').next().unwrap_or_default();
+        let key_versions = self
+            .fetch_all_key_versions(self.server_key_prefix(Some(literal_prefix)))
+            .await
+            .map_err(|e| convert_error(e, "delete_matching"))?;
+
+        let mut keys = Vec::new();
+        let mut delete_items = Vec::new();
+        for kv in key_versions {
+            let Some(key) = self.extract_key_with_prefix(&kv.key, Some(literal_prefix))? else {
+                continue;
+            };
+            if matches_pattern(&pattern, &key) {
+                keys.push(key);
+                delete_items.push(ExternalKeyValue { key: kv.key, version: -1, value: vec![] });
+            }
+        }
+
+        for chunk in chunk_items(delete_items, self.config.max_batch_items as usize, 0) {
+            self.delete_items(chunk)
+                .await
+                .map_err(|e| convert_error(e, "delete_matching"))?;
+        }
+
+        Ok(keys)
+    }
+
+    /// Deletes the keys under a prefix whose version is at most `max_version`.
+    ///
+    /// Meant for incremental garbage collection: keys written after a known version are
+    /// kept. Deletes are batched like `clear_store` but conditional on the listed version,
+    /// so a key updated after the listing survives. If a batch hits such a conflict, its
+    /// keys are retried one by one and the updated ones are skipped.
+    ///
+    /// Can partially complete when cancelled: the handle is checked before each batch and
+    /// each one-by-one retry, and keys already deleted stay deleted.
+    ///
+    /// # Parameters
+    /// - `prefix`: Only keys starting with this prefix are considered
+    /// - `max_version`: Keys at this version or below are deleted
+    /// - `cancel`: Optional handle that stops the deletion with `VssError::Cancelled` once cancelled
+    ///
+    /// # Returns
+    /// The number of keys deleted
+    pub async fn delete_prefix_older_than(
+        &self,
+        prefix: String,
+        max_version: i64,
+        cancel: Option<Arc<VssCancelHandle>>,
+    ) -> Result<u32, VssError> {
+        self.check_writable()?;
+        let key_versions = self
+            .fetch_all_key_versions(self.server_key_prefix(Some(&prefix)))
+            .await
+            .map_err(|e| convert_error(e, "delete_prefix_older_than"))?;
+
+        let mut delete_items = Vec::new();
+        for kv in key_versions {
+            if self.extract_key_with_prefix(&kv.key, Some(&prefix))?.is_some()
+                && kv.version <= max_version
+            {
+                delete_items.push(ExternalKeyValue { key: kv.key, version: kv.version, value: vec![] });
+            }
+        }
+
+        let mut deleted = 0u32;
+        for chunk in chunk_items(delete_items, self.config.max_batch_items as usize, 0) {
+            check_cancelled(cancel.as_deref())?;
+            let count = chunk.len() as u32;
+            match self.delete_items(chunk.clone()).await {
+                Ok(()) => deleted += count,
+                Err(BackendError { error: ExternalVssError::ConflictError(_), .. }) => {
+                    for item in chunk {
+                        check_cancelled(cancel.as_deref())?;
+                        match self.delete_items(vec![item]).await {
+                            Ok(()) => deleted += 1,
+                            Err(BackendError { error: ExternalVssError::ConflictError(_), .. }) => {}
+                            Err(e) => return Err(convert_error(e, "delete_prefix_older_than")),
+                        }
+                    }
+                }
+                Err(e) => return Err(convert_error(e, "delete_prefix_older_than")),
+            }
+        }
+
+        Ok(deleted)
+    }
+
+    /// Deletes storage keys in one transaction, recording them for `get_consistent`
+    async fn delete_items(&self, delete_items: Vec<ExternalKeyValue>) -> Result<(), BackendError> {
+        let request = PutObjectRequest {
+            store_id: self.store_id.clone(),
+            global_version: None,
+            transaction_items: vec![],
+            delete_items,
+        };
+        self.inner.put_object(&request).await?;
+        self.remember_writes(&request);
+        Ok(())
+    }
+
+    /// Re-encrypts every item in the store under keys derived from a new seed.
+    ///
+    /// Each item is read with the current encryption settings and written back under the
+    /// new ones in a single transaction that also deletes the old storage key. On success
+    /// the client switches to the new keys. Rotation always covers the whole store, even
+    /// when called on a prefixed view.
+    ///
+    /// If an item fails midway, items already processed remain under the new keys while
+    /// this client keeps the old ones; the error carries a client with the new keys, which
+    /// reads those. Calling this again with the same seed resumes the rotation: items that
+    /// already decrypt under the new keys are skipped.
+    ///
+    /// # Parameters
+    /// - `new_seed`: The seed bytes for the new key derivation (32 bytes)
+    ///
+    /// # Returns
+    /// The number of items that were re-encrypted by this call
+    pub async fn rotate_encryption(&mut self, new_seed: [u8; 32]) -> Result<u32, RotationError> {
+        self.check_writable()?;
+        let vss_xprv = derive_vss_xprv(&SECP, &new_seed)?;
+        let (data_encryption_key, obfuscation_master_key) =
+            derive_data_encryption_and_obfuscation_keys(
+                &vss_xprv.private_key.secret_bytes(),
+                &self.config,
+            );
+        let rotated_client = VssClient {
+            storable_builder: Arc::new(StorableBuilder::new(data_encryption_key, self.entropy.clone())),
+            key_obfuscator: self
+                .config
+                .obfuscate_keys
+                .then(|| Arc::new(KeyObfuscator::new(obfuscation_master_key))),
+            encrypted: true,
+            last_writes: Arc::new(Mutex::new(LruMap::new(MAX_TRACKED_WRITES))),
+            debounced: Arc::new(Mutex::new(DebouncedWrites::default())),
+            cache: Arc::new(Mutex::new(LruMap::new(MAX_CACHED_ITEMS))),
+            ..self.clone()
+        };
+
+        let mut rotated = 0u32;
+        let result = self.rotate_items(&rotated_client, &mut rotated).await;
+        if let Err(error) = result {
+            return Err(RotationError { error, rotated, rotated_client: Some(rotated_client) });
+        }
+
+        self.storable_builder = rotated_client.storable_builder;
+        self.key_obfuscator = rotated_client.key_obfuscator;
+        self.encrypted = true;
+        self.last_writes.lock().unwrap().clear();
+        self.cache.lock().unwrap().clear();
+
+        Ok(rotated)
+    }
+
+    /// Implements `rotate_encryption`, rewriting every item still under this client's keys
+    /// with those of `target` and counting them in `rotated`
+    async fn rotate_items(&self, target: &VssClient, rotated: &mut u32) -> Result<(), VssError> {
+        let key_versions = self
+            .fetch_all_key_versions(None)
+            .await
+            .map_err(|e| convert_error(e, "rotate_encryption"))?;
+
+        for key_version in key_versions {
+            // A key only the new obfuscator reads was rotated by an earlier, interrupted run
+            let original_key = match self.deobfuscate_key(&key_version.key) {
+                Ok(key) => key,
+                Err(_) if target.deobfuscate_key(&key_version.key).is_ok() => continue,
+                Err(e) => return Err(e),
+            };
+
+            let request = GetObjectRequest {
+                store_id: self.store_id.clone(),
+                key: key_version.key.clone(),
+            };
+            let stored = match self.inner.get_object(&request).await {
+                Ok(response) => match response.value {
+                    Some(kv) => kv,
+                    None => continue,
+                },
+                Err(BackendError { error: ExternalVssError::NoSuchKeyError(_), .. }) => continue,
+                Err(e) => return Err(convert_error(e, "rotate_encryption")),
+            };
+
+            // Decrypted as is, so a stored content type carries over. With plaintext keys a
+            // rotated item keeps its key, so only its value tells it was rotated already.
+            let value = match self.decrypt_value(&original_key, &stored.value) {
+                Ok(value) => value,
+                Err(_) if target.decrypt_value(&original_key, &stored.value).is_ok() => continue,
+                Err(e) => return Err(e),
+            };
+            let new_key = match target.key_obfuscator {
+                Some(ref obfuscator) => obfuscator.obfuscate(&original_key),
+                None => original_key,
+            };
+
+            // With plaintext keys the item is rewritten in place, conditionally on the
+            // version read; otherwise the old storage key is deleted alongside.
+            let new_builder = &target.storable_builder;
+            let request = if new_key == stored.key {
+                PutObjectRequest {
+                    store_id: self.store_id.clone(),
+                    global_version: None,
+                    transaction_items: vec![ExternalKeyValue {
+                        key: new_key,
+                        version: stored.version,
+                        value: new_builder.build(value, stored.version).encode_to_vec(),
+                    }],
+                    delete_items: vec![],
+                }
+            } else {
+                PutObjectRequest {
+                    store_id: self.store_id.clone(),
+                    global_version: None,
+                    transaction_items: vec![ExternalKeyValue {
+                        key: new_key,
+                        version: -1,
+                        value: new_builder.build(value, -1).encode_to_vec(),
+                    }],
+                    delete_items: vec![ExternalKeyValue {
+                        key: stored.key,
+                        version: stored.version,
+                        value: vec![],
+                    }],
+                }
+            };
+            self.inner
+                .put_object(&request)
+                .await
+                .map_err(|e| convert_error(e, "rotate_encryption"))?;
+
+            *rotated += 1;
+        }
+
+        Ok(())
+    }
+
+    /// Pages through `list_key_versions` until the server returns an empty page or no page token
+    pub(crate) async fn fetch_all_key_versions(
+        &self,
+        key_prefix: Option<String>,
+    ) -> Result<Vec<ExternalKeyValue>, BackendError> {
+        let mut key_versions = Vec::new();
+        let mut page_token = None;
+
+        loop {
+            let request = ListKeyVersionsRequest {
+                store_id: self.store_id.clone(),
+                key_prefix: key_prefix.clone(),
+                page_size: Some(self.page_size() as i32),
+                page_token,
+            };
+            let response = self.inner.list_key_versions(&request).await?;
+            if response.key_versions.is_empty() {
+                break;
+            }
+            key_versions.extend(response.key_versions);
+
+            match response.next_page_token {
+                Some(token) if !token.is_empty() => page_token = Some(token),
+                _ => break,
+            }
+        }
+
+        Ok(key_versions)
+    }
+
+    /// Keys to request per page when paging through a listing internally
+    fn page_size(&self) -> u32 {
+        self.config.default_page_size.unwrap_or(DEFAULT_PAGE_SIZE)
+    }
+
+    /// Wraps a value in an encrypted `Storable` envelope and serializes it for storage
+    pub(crate) fn encode_value(&self, value: Vec<u8>, version: i64) -> Vec<u8> {
+        self.encode_value_with_content_type(value, None, version)
+    }
+
+    /// Like `encode_value`, prefixing the value with a content type header inside the
+    /// encrypted envelope when one is given. Without one, values that could be mistaken
+    /// for a header are escaped instead.
+    fn encode_value_with_content_type(
+        &self,
+        value: Vec<u8>,
+        content_type: Option<&str>,
+        version: i64,
+    ) -> Vec<u8> {
+        let plaintext = match content_type {
+            Some(content_type) => {
+                let mut plaintext = CONTENT_TYPE_MAGIC.to_vec();
+                plaintext.push(content_type.len() as u8);
+                plaintext.extend_from_slice(content_type.as_bytes());
+                plaintext.extend_from_slice(&value);
+                plaintext
+            }
+            None if value.starts_with(RESERVED_VALUE_PREFIX) => {
+                let mut plaintext = ESCAPE_MAGIC.to_vec();
+                plaintext.extend_from_slice(&value);
+                plaintext
+            }
+            None => value,
+        };
+        let plaintext = if self.config.verify_integrity {
+            let mut checksummed = CHECKSUM_MAGIC.to_vec();
+            checksummed.extend_from_slice(sha256::Hash::hash(&plaintext).as_byte_array());
+            checksummed.extend_from_slice(&plaintext);
+            checksummed
+        } else {
+            plaintext
         };
+        self.storable_builder.build(plaintext, version).encode_to_vec()
+    }
 
-        match self.inner.list_key_versions(&request).await {
-            Ok(list_response) => {
-                let mut items = Vec::new();
+    /// Returns the store behind an in-memory client, for injecting failures
+    #[cfg(test)]
+    pub(crate) fn in_memory_store(&self) -> &InMemoryStore {
+        match self.inner.as_ref() {
+            Backend::InMemory(store) => store,
+            _ => panic!("not an in-memory client"),
+        }
+    }
 
-                for key_version in list_response.key_versions {
-                    let original_key = self.extract_key(&key_version.key)?;
+    /// Returns a client on the same backend that shares no client-side state, standing in
+    /// for another device writing to the same store
+    #[cfg(test)]
+    pub(crate) fn second_writer(&self) -> VssClient {
+        VssClient {
+            last_writes: Arc::new(Mutex::new(LruMap::new(MAX_TRACKED_WRITES))),
+            debounced: Arc::new(Mutex::new(DebouncedWrites::default())),
+            cache: Arc::new(Mutex::new(LruMap::new(MAX_CACHED_ITEMS))),
+            ..self.clone()
+        }
+    }
 
-                    if let Ok(Some(item)) = self.get(original_key).await {
-                        items.push(item);
-                    }
-                }
+    /// Encrypts a plaintext as is, without the headers and escaping of `encode_value`
+    #[cfg(test)]
+    pub(crate) fn encode_plaintext(&self, plaintext: Vec<u8>, version: i64) -> Vec<u8> {
+        self.storable_builder.build(plaintext, version).encode_to_vec()
+    }
+
+    /// Decodes and decrypts a stored `Storable` envelope back into the user value
+    #[cfg(test)]
+    pub(crate) fn decode_value(&self, key: &str, stored: &[u8]) -> Result<Vec<u8>, VssError> {
+        Ok(self.decode_value_with_content_type(key, stored)?.0)
+    }
+
+    /// Like `decode_value`, also returning the content type stored with the value
+    fn decode_value_with_content_type(
+        &self,
+        key: &str,
+        stored: &[u8],
+    ) -> Result<(Vec<u8>, Option<String>), VssError> {
+        let plaintext = verify_checksum(key, self.decrypt_value(key, stored)?)?;
+        Ok(split_content_type(plaintext))
+    }
 
-                Ok(items)
+    /// Decodes and decrypts a stored `Storable` envelope, keeping any content type header
+    fn decrypt_value(&self, key: &str, stored: &[u8]) -> Result<Vec<u8>, VssError> {
+        if let Some(max_value_bytes) = self.config.max_value_bytes {
+            if stored.len() as u64 > max_value_bytes {
+                return Err(VssError::InvalidData {
+                    error_details: format!(
+                        "Stored value of key '{}' is {} bytes, exceeding the maximum of {}",
+                        display_key(key),
+                        stored.len(),
+                        max_value_bytes
+                    ),
+                });
             }
-            Err(e) => Err(convert_error(e, "list")),
         }
+
+        decrypt_storable(&self.storable_builder, self.encrypted, key, stored)
     }
 
-    /// Lists keys and versions without retrieving values.
-    ///
-    /// # Parameters
-    /// - `prefix`: Optional key prefix filter
+    /// Checks that a user key is non-empty and, including any view prefix, within the
+    /// configured length limit
+    pub(crate) fn validate_key(&self, key: &str) -> Result<(), VssError> {
+        if key.is_empty() {
+            return Err(VssError::InvalidData {
+                error_details: "Key must not be empty".to_string(),
+            });
+        }
+
+        let max_key_length = self.config.max_key_length as usize;
+        let key_length = self.key_prefix.len() + key.len();
+        if key_length > max_key_length {
+            return Err(VssError::InvalidData {
+                error_details: format!(
+                    "Key length {} exceeds maximum of {} bytes",
+                    key_length,
+                    max_key_length
+                ),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Fails with `VssError::Unsupported` when the client is read-only. Write paths call this
+    /// before sending anything, including the listings some of them start with.
+    fn check_writable(&self) -> Result<(), VssError> {
+        if self.config.read_only {
+            return Err(VssError::Unsupported { feature: "read_only".to_string() });
+        }
+        Ok(())
+    }
+
+    /// Checks a user key for writing, additionally rejecting the reserved namespace and
+    /// any write on a read-only client
+    pub(crate) fn validate_write_key(&self, key: &str) -> Result<(), VssError> {
+        self.check_writable()?;
+        self.validate_key(key)?;
+
+        if self.full_key(key).starts_with(RESERVED_KEY_PREFIX) {
+            return Err(VssError::InvalidData {
+                error_details: format!(
+                    "Keys starting with '{}' are reserved for internal use",
+                    RESERVED_KEY_PREFIX
+                ),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Returns this client without its view prefix, for operating on full keys
+    fn root(&self) -> VssClient {
+        let mut root = self.clone();
+        root.key_prefix.clear();
+        root
+    }
+
+    /// Prepends the view prefix to a user key
+    fn full_key(&self, key: &str) -> String {
+        format!("{}{}", self.key_prefix, key)
+    }
+
+    /// Converts a user key to storage key (obfuscated if encryption is enabled)
+    pub(crate) fn build_key(&self, key: &str) -> String {
+        self.build_storage_key(&self.full_key(&self.stored_key_name(key)))
+    }
+
+    /// Replaces a key whose full form exceeds `max_plain_key_len` with its hashed form
+    /// when `hash_long_keys` is set. Hashed forms are passed through unchanged.
+    fn stored_key_name<'a>(&self, key: &'a str) -> Cow<'a, str> {
+        let too_long = self.key_prefix.len() + key.len() > self.config.max_plain_key_len as usize;
+        if !self.config.hash_long_keys || !too_long || is_hashed_key(key) {
+            return Cow::Borrowed(key);
+        }
+
+        Cow::Owned(format!("{}{}", HASHED_KEY_PREFIX, sha256::Hash::hash(key.as_bytes())))
+    }
+
+    /// Builds the server-side prefix filter for a list request, covering the view prefix.
     ///
-    /// # Returns
-    /// Vector of KeyVersion structs (more efficient than list())
-    pub async fn list_keys(&self, prefix: Option<String>) -> Result<Vec<KeyVersion>, VssError> {
-        let request = ListKeyVersionsRequest {
-            store_id: self.store_id.clone(),
-            key_prefix: prefix.as_ref().map(|p| self.build_key(p)),
-            page_size: None,
-            page_token: None,
-        };
+    /// None when keys are obfuscated, since obfuscation doesn't preserve prefixes. Every
+    /// listing must therefore also filter the keys it gets back by user key.
+    fn server_key_prefix(&self, prefix: Option<&str>) -> Option<String> {
+        let full_prefix = self.full_key(prefix.unwrap_or_default());
+        if full_prefix.is_empty() || self.key_obfuscator.is_some() {
+            None
+        } else {
+            Some(full_prefix)
+        }
+    }
 
-        match self.inner.list_key_versions(&request).await {
-            Ok(response) => {
-                let mut result = Vec::new();
-                for kv in response.key_versions {
-                    let original_key = self.extract_key(&kv.key)?;
-
-                    result.push(KeyVersion {
-                        key: original_key,
-                        version: kv.version,
-                    });
-                }
-                Ok(result)
-            }
-            Err(e) => Err(convert_error(e, "list_keys")),
+    /// Converts a storage key back to user key, or None if it lies outside the view prefix
+    /// or doesn't start with `prefix`
+    fn extract_key_with_prefix(
+        &self,
+        storage_key: &str,
+        prefix: Option<&str>,
+    ) -> Result<Option<String>, VssError> {
+        let key = self.extract_key(storage_key)?;
+        Ok(key.filter(|key| key.starts_with(prefix.unwrap_or_default())))
+    }
+
+    /// Obfuscates a full key, ignoring the view prefix
+    fn build_storage_key(&self, full_key: &str) -> String {
+        if let Some(ref obfuscator) = self.key_obfuscator {
+            obfuscator.obfuscate(full_key)
+        } else {
+            full_key.to_string()
+        }
+    }
+
+    /// Converts a storage key back to user key, or None if it lies outside the view prefix
+    fn extract_key(&self, storage_key: &str) -> Result<Option<String>, VssError> {
+        let full_key = self.deobfuscate_key(storage_key)?;
+        Ok(full_key.strip_prefix(&self.key_prefix).map(str::to_string))
+    }
+
+    /// Converts a storage key back to the full key (deobfuscated if encryption is enabled)
+    fn deobfuscate_key(&self, storage_key: &str) -> Result<String, VssError> {
+        if let Some(ref obfuscator) = self.key_obfuscator {
+            obfuscator.deobfuscate(storage_key).map_err(|e| VssError::ListError {
+                error_details: format!(
+                    "Failed to deobfuscate key, it was likely written with a different seed or \
+                     without key obfuscation: {}",
+                    e
+                ),
+            })
+        } else {
+            Ok(storage_key.to_string())
         }
     }
+}
 
-    /// Stores multiple key-value pairs in an atomic transaction.
+/// One line of an `export_ndjson` export
+#[derive(Serialize, Deserialize)]
+struct NdjsonRecord {
+    key: String,
+    value_base64: String,
+    version: i64,
+}
+
+impl VssClient {
+    /// Exports the items under a prefix as newline-delimited JSON.
+    ///
+    /// Each line is an object with the item's `key`, its decrypted value as standard
+    /// base64 in `value_base64`, and its `version`, ordered by key so exports can be
+    /// diffed. The output holds the store contents in plaintext.
     ///
     /// # Parameters
-    /// - `items`: Vector of KeyValue pairs to store
+    /// - `prefix`: Optional key prefix filter
+    /// - `progress`: Optional receiver of the number of items read out of the number of
+    ///   keys listed, first with none read
     ///
     /// # Returns
-    /// Vector of stored VssItems with assigned versions
-    pub async fn put_with_key_prefix(
+    /// The export, one line per item, each terminated by a newline
+    pub async fn export_ndjson(
         &self,
-        items: Vec<KeyValue>,
-    ) -> Result<Vec<VssItem>, VssError> {
-        let version = -1;
-        let external_items: Vec<ExternalKeyValue> = items
-            .iter()
-            .map(|item| {
-                let storable = self.storable_builder.build(item.value.clone(), version);
-                ExternalKeyValue {
-                    key: self.build_key(&item.key),
-                    value: storable.encode_to_vec(),
-                    version,
-                }
-            })
-            .collect();
+        prefix: Option<String>,
+        progress: Option<&dyn VssProgress>,
+    ) -> Result<String, VssError> {
+        let mut keys: Vec<String> =
+            self.list_keys(prefix).await?.into_iter().map(|kv| kv.key).collect();
+        keys.sort();
+        if let Some(progress) = progress {
+            progress.on_progress(0, keys.len() as u64);
+        }
 
-        let request = PutObjectRequest {
-            store_id: self.store_id.clone(),
-            global_version: None,
-            transaction_items: external_items,
-            delete_items: vec![],
-        };
+        let mut items = Vec::with_capacity(keys.len());
+        for (_, result) in self.fetch_results(keys, None, progress).await? {
+            items.extend(result?);
+        }
 
-        match self.inner.put_object(&request).await {
-            Ok(_response) => {
-                Ok(items
-                    .into_iter()
-                    .map(|item| VssItem {
-                        key: item.key,
-                        value: item.value,
-                        version: -1,
-                    })
-                    .collect())
-            }
-            Err(e) => Err(convert_error(e, "put_with_key_prefix")),
+        let mut ndjson = String::new();
+        for item in items {
+            let record = NdjsonRecord {
+                key: item.key,
+                value_base64: BASE64.encode(&item.value),
+                version: item.version,
+            };
+            let line = serde_json::to_string(&record).map_err(|e| VssError::InvalidData {
+                error_details: format!("Failed to serialize item '{}': {}", display_key(&record.key), e),
+            })?;
+            ndjson.push_str(&line);
+            ndjson.push('\n');
         }
+
+        Ok(ndjson)
     }
 
-    /// Deletes a key-value pair.
+    /// Imports items from newline-delimited JSON produced by `export_ndjson`.
+    ///
+    /// All lines are parsed before anything is written, so malformed input writes nothing.
+    /// Blank lines are skipped. Values are stored unconditionally and batched like
+    /// `put_with_key_prefix`; the exported versions are ignored, since versions can't be
+    /// set on the server.
     ///
     /// # Parameters
-    /// - `key`: The key to delete
+    /// - `ndjson`: The export to import
+    /// - `progress`: Optional receiver of the number of items written out of the number of
+    ///   items in the export, first with none written and then after each batch
     ///
     /// # Returns
-    /// true if deleted, false if key didn't exist
-    pub async fn delete(&self, key: String) -> Result<bool, VssError> {
-        let request = DeleteObjectRequest {
-            store_id: self.store_id.clone(),
-            key_value: Some(ExternalKeyValue {
-                key: self.build_key(&key),
-                version: -1,
-                value: vec![],
-            }),
-        };
+    /// The number of items written
+    pub async fn import_ndjson(
+        &self,
+        ndjson: &str,
+        progress: Option<&dyn VssProgress>,
+    ) -> Result<u32, VssError> {
+        self.check_writable()?;
+        let mut items = Vec::new();
+        for (index, line) in ndjson.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
 
-        match self.inner.delete_object(&request).await {
-            Ok(_) => Ok(true),
-            Err(ExternalVssError::NoSuchKeyError(_)) => Ok(false),
-            Err(e) => Err(convert_error(e, "delete")),
+            let invalid = |details: String| VssError::InvalidData {
+                error_details: format!("Invalid NDJSON on line {}: {}", index + 1, details),
+            };
+            let record: NdjsonRecord =
+                serde_json::from_str(line).map_err(|e| invalid(e.to_string()))?;
+            let value = BASE64
+                .decode(&record.value_base64)
+                .map_err(|e| invalid(e.to_string()))?;
+            items.push(KeyValue { key: record.key, value });
         }
+
+        let count = items.len() as u32;
+        if let Some(progress) = progress {
+            progress.on_progress(0, count as u64);
+        }
+        if let (_, Some((_, error))) = self.put_batched(items, progress).await? {
+            return Err(error);
+        }
+        Ok(count)
+    }
+}
+
+/// Whether a key is the hashed form of a long key, as produced by `stored_key_name`
+fn is_hashed_key(key: &str) -> bool {
+    key.strip_prefix(HASHED_KEY_PREFIX)
+        .is_some_and(|digest| digest.len() == 64 && digest.bytes().all(|b| b.is_ascii_hexdigit()))
+}
+
+/// Trims a server URL and strips trailing slashes, since request paths are appended with
+/// their own `/`. Fails with ConnectionError unless it is an absolute http or https URL
+/// with a host; an empty URL is accepted as is when `allow_empty` is set.
+fn normalize_base_url(base_url: &str, allow_empty: bool) -> Result<String, VssError> {
+    let trimmed = base_url.trim().trim_end_matches('/');
+    if trimmed.is_empty() && allow_empty {
+        return Ok(String::new());
     }
 
-    /// Converts a user key to storage key (obfuscated if encryption is enabled)
-    fn build_key(&self, key: &str) -> String {
-        if let Some(ref obfuscator) = self.key_obfuscator {
-            obfuscator.obfuscate(key)
-        } else {
-            key.to_string()
+    let url = Url::parse(trimmed).map_err(|e| VssError::ConnectionError {
+        error_details: format!("Invalid base URL {:?}: {}", base_url, e),
+    })?;
+    if !matches!(url.scheme(), "http" | "https") || url.host_str().is_none() {
+        return Err(VssError::ConnectionError {
+            error_details: format!("Base URL {:?} must be an http or https URL", base_url),
+        });
+    }
+    Ok(trimmed.to_string())
+}
+
+/// Checks the config settings that have no meaningful value outside a range
+fn validate_config(config: &VssClientConfig) -> Result<(), VssError> {
+    // Page sizes are sent as a signed 32-bit integer. Servers don't report their own
+    // limit, so larger pages are left for the server to cap.
+    if let Some(page_size) = config.default_page_size {
+        if page_size == 0 || page_size > i32::MAX as u32 {
+            return Err(VssError::InvalidData {
+                error_details: format!(
+                    "default_page_size must be between 1 and {}, got {}",
+                    i32::MAX,
+                    page_size
+                ),
+            });
         }
     }
+    Ok(())
+}
 
-    /// Converts a storage key back to user key (deobfuscated if encryption is enabled)
-    fn extract_key(&self, storage_key: &str) -> Result<String, VssError> {
-        if let Some(ref obfuscator) = self.key_obfuscator {
-            obfuscator.deobfuscate(storage_key).map_err(|e| VssError::ListError {
-                error_details: format!("Failed to deobfuscate key: {}", e),
-            })
-        } else {
-            Ok(storage_key.to_string())
+/// Parses a `proxy_url` setting, accepting the http, https, socks5 and socks5h schemes
+fn build_proxy(proxy_url: &str) -> Result<reqwest::Proxy, VssError> {
+    let url = Url::parse(proxy_url).map_err(|e| VssError::ConnectionError {
+        error_details: format!("Invalid proxy URL {}: {}", proxy_url, e),
+    })?;
+    if !matches!(url.scheme(), "http" | "https" | "socks5" | "socks5h") {
+        return Err(VssError::ConnectionError {
+            error_details: format!("Unsupported proxy URL scheme: {}", proxy_url),
+        });
+    }
+    if url.host_str().is_none_or(str::is_empty) {
+        return Err(VssError::ConnectionError {
+            error_details: format!("Proxy URL has no host: {}", proxy_url),
+        });
+    }
+    reqwest::Proxy::all(proxy_url).map_err(|e| VssError::ConnectionError {
+        error_details: format!("Invalid proxy URL {}: {}", proxy_url, e),
+    })
+}
+
+/// Builds the HTTP client of clients without a header provider, with the user agent and
+/// the connect and request timeouts of `config`. An explicit proxy replaces the proxies
+/// from the environment.
+fn build_http_client(
+    user_agent: &str,
+    config: &VssClientConfig,
+    pinning: Option<PinnedCertVerifier>,
+    proxy: Option<reqwest::Proxy>,
+) -> Result<reqwest::Client, VssError> {
+    let mut builder = reqwest::Client::builder().user_agent(user_agent);
+    if let Some(timeout_ms) = config.connect_timeout_ms {
+        builder = builder.connect_timeout(Duration::from_millis(timeout_ms));
+    }
+    if let Some(timeout_ms) = config.read_timeout_ms {
+        builder = builder.timeout(Duration::from_millis(timeout_ms));
+    }
+    if let Some(verifier) = pinning {
+        let tls = rustls::ClientConfig::builder()
+            .with_safe_defaults()
+            .with_custom_certificate_verifier(Arc::new(verifier))
+            .with_no_client_auth();
+        builder = builder.use_preconfigured_tls(tls);
+    }
+    if let Some(proxy) = proxy {
+        builder = builder.proxy(proxy);
+    }
+    builder.build().map_err(|e| VssError::ConnectionError {
+        error_details: format!("Failed to build HTTP client: {}", e),
+    })
+}
+
+/// Checks that a content type fits in the one-byte length of its header
+fn validate_content_type(content_type: &str) -> Result<(), VssError> {
+    if content_type.len() > u8::MAX as usize {
+        return Err(VssError::InvalidData {
+            error_details: format!(
+                "Content type length {} exceeds maximum of {} bytes",
+                content_type.len(),
+                u8::MAX
+            ),
+        });
+    }
+    Ok(())
+}
+
+/// Decodes a stored `Storable` envelope and decrypts it, keeping any headers in the
+/// plaintext. `key` names the value in errors; `encrypted` tells whether `builder` holds a
+/// key derived from a seed rather than the zero key.
+fn decrypt_storable(
+    builder: &StorableBuilder<ClientEntropySource>,
+    encrypted: bool,
+    key: &str,
+    stored: &[u8],
+) -> Result<Vec<u8>, VssError> {
+    let storable = Storable::decode(stored).map_err(|_| VssError::DecodeError {
+        key: key.to_string(),
+    })?;
+
+    // Data written without a `StorableBuilder` has no encryption metadata at all.
+    let Some(ref metadata) = storable.encryption_metadata else {
+        return Err(VssError::EncryptionMismatch {
+            key: key.to_string(),
+        });
+    };
+
+    // The cipher format is the only version marker the envelope carries. A format this
+    // crate doesn't know would decrypt to garbage or fail as a key mismatch.
+    if metadata.cipher_format != SUPPORTED_CIPHER_FORMAT {
+        return Err(VssError::Unsupported {
+            feature: format!(
+                "envelope format '{}' of key '{}'",
+                metadata.cipher_format, display_key(key)
+            ),
+        });
+    }
+
+    // A failed authentication tag means the value was encrypted under a different key, or
+    // corrupted. Clients created without a seed encrypt under the zero key, so a value that
+    // opens with it tells the two apart from a different seed.
+    match builder.deconstruct(storable) {
+        Ok((decrypted_value, _)) => Ok(decrypted_value),
+        Err(_) if encrypted && !opens_with_zero_key(stored) => Err(VssError::DecryptError {
+            key: key.to_string(),
+        }),
+        Err(_) => Err(VssError::EncryptionMismatch {
+            key: key.to_string(),
+        }),
+    }
+}
+
+/// Whether a stored envelope decrypts under the zero key of clients created without a seed
+fn opens_with_zero_key(stored: &[u8]) -> bool {
+    let Ok(storable) = Storable::decode(stored) else {
+        return false;
+    };
+    StorableBuilder::new([0u8; 32], ClientEntropySource::Rand)
+        .deconstruct(storable)
+        .is_ok()
+}
+
+/// Strips the integrity header written by `encode_value_with_content_type`, checking the
+/// rest of the plaintext against it. Values without a header are returned as is.
+fn verify_checksum(key: &str, plaintext: Vec<u8>) -> Result<Vec<u8>, VssError> {
+    let Some(rest) = plaintext.strip_prefix(CHECKSUM_MAGIC) else {
+        return Ok(plaintext);
+    };
+    if rest.len() < 32 {
+        return Err(VssError::IntegrityError {
+            key: key.to_string(),
+        });
+    }
+
+    let (checksum, value) = rest.split_at(32);
+    if sha256::Hash::hash(value).as_byte_array() != checksum {
+        return Err(VssError::IntegrityError {
+            key: key.to_string(),
+        });
+    }
+    Ok(value.to_vec())
+}
+
+/// Splits a decrypted value into the user value and the content type header written by
+/// `encode_value_with_content_type`, removing the escape in front of values that start
+/// like a header. Values without a valid header, written before escaping, are returned
+/// as is.
+fn split_content_type(plaintext: Vec<u8>) -> (Vec<u8>, Option<String>) {
+    if let Some(value) = plaintext.strip_prefix(ESCAPE_MAGIC) {
+        return (value.to_vec(), None);
+    }
+    let Some(rest) = plaintext.strip_prefix(CONTENT_TYPE_MAGIC) else {
+        return (plaintext, None);
+    };
+    let Some((&length, rest)) = rest.split_first() else {
+        return (plaintext, None);
+    };
+    if rest.len() < length as usize {
+        return (plaintext, None);
+    }
+
+    let (content_type, value) = rest.split_at(length as usize);
+    match std::str::from_utf8(content_type) {
+        Ok(content_type) => (value.to_vec(), Some(content_type.to_string())),
+        Err(_) => (plaintext, None),
+    }
+}
+
+/// Whether a key matches a `delete_matching` pattern in full, `*` matching any run of
+/// characters
+fn matches_pattern(pattern: &str, key: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let Some(mut rest) = key.strip_prefix(parts.next().unwrap_or_default()) else {
+        return false;
+    };
+
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+/// The key of chunk `index` of a value stored with `store_large`
+fn large_value_chunk_key(key: &str, generation: Option<&str>, index: u32) -> String {
+    match generation {
+        Some(generation) => format!("{}/chunk/{}/{}", key, generation, index),
+        None => format!("{}/chunk/{}", key, index),
+    }
+}
+
+/// Splits items into batches of at most `max_items` items and `max_bytes` bytes.
+///
+/// Sizes count keys and encoded values. An item larger than `max_bytes` on its own is
+/// sent in a batch by itself and left for the server to accept or reject. A limit of 0
+/// disables that limit.
+pub(crate) fn chunk_items(
+    items: Vec<ExternalKeyValue>,
+    max_items: usize,
+    max_bytes: usize,
+) -> Vec<Vec<ExternalKeyValue>> {
+    let mut chunks = Vec::new();
+    let mut chunk: Vec<ExternalKeyValue> = Vec::new();
+    let mut chunk_bytes = 0;
+
+    for item in items {
+        let item_bytes = item.key.len() + item.value.len();
+        let full = (max_items > 0 && chunk.len() >= max_items)
+            || (max_bytes > 0 && chunk_bytes + item_bytes > max_bytes);
+        if full && !chunk.is_empty() {
+            chunks.push(std::mem::take(&mut chunk));
+            chunk_bytes = 0;
         }
+        chunk_bytes += item_bytes;
+        chunk.push(item);
+    }
+    if !chunk.is_empty() {
+        chunks.push(chunk);
+    }
+
+    chunks
+}
+
+/// Unwraps the result of a concurrent get spawned by `fetch_results`
+fn joined_result(
+    joined: Result<(usize, String, FetchResult), tokio::task::JoinError>,
+) -> Result<(usize, String, FetchResult), VssError> {
+    joined.map_err(|e| VssError::UnknownError {
+        error_details: format!("Fetch task failed: {}", e),
+    })
+}
+
+/// Fails with `VssError::Cancelled` once a batch operation's cancel handle is cancelled
+fn check_cancelled(cancel: Option<&VssCancelHandle>) -> Result<(), VssError> {
+    match cancel {
+        Some(handle) if handle.is_cancelled() => Err(VssError::Cancelled),
+        _ => Ok(()),
     }
 }
 
-/// Derives data encryption and obfuscation keys from VSS seed
-fn derive_data_encryption_and_obfuscation_keys(vss_seed: &[u8; 32]) -> ([u8; 32], [u8; 32]) {
+/// Unwraps the result of a concurrent lookup spawned by `exists_many`
+fn joined_exists(
+    joined: Result<(usize, Result<bool, VssError>), tokio::task::JoinError>,
+) -> Result<(usize, bool), VssError> {
+    let (index, result) = joined.map_err(|e| VssError::UnknownError {
+        error_details: format!("Lookup task failed: {}", e),
+    })?;
+    Ok((index, result?))
+}
+
+/// Unwraps the result of a spawned request
+fn joined_task<T>(joined: Result<Result<T, VssError>, tokio::task::JoinError>) -> Result<T, VssError> {
+    joined.map_err(|e| VssError::UnknownError {
+        error_details: format!("Request task failed: {}", e),
+    })?
+}
+
+/// Derives the VSS root key (`m/877'`) from a 32-byte master seed.
+///
+/// The network passed to `Xpriv::new_master` only selects the version bytes used when
+/// serializing the key; derived private keys are the same on every network. The LNURL-auth
+/// key, and so the linking keys a server sees, are therefore identical for mainnet,
+/// testnet, signet and regtest, and there is no network to configure.
+fn derive_vss_xprv(secp: &Secp256k1<All>, seed: &[u8; 32]) -> Result<Xpriv, VssError> {
+    let master_xprv =
+        Xpriv::new_master(Network::Bitcoin, seed).map_err(|e| VssError::ConnectionError {
+            error_details: format!("Failed to create master key: {}", e),
+        })?;
+
+    master_xprv
+        .derive_priv(
+            secp,
+            &[ChildNumber::Hardened {
+                index: VSS_HARDENED_CHILD_INDEX,
+            }],
+        )
+        .map_err(|e| VssError::ConnectionError {
+            error_details: format!("Failed to derive VSS key: {}", e),
+        })
+}
+
+/// Derives data encryption and obfuscation keys from VSS seed, using the HKDF salts in `config`
+fn derive_data_encryption_and_obfuscation_keys(
+    vss_seed: &[u8; 32],
+    config: &VssClientConfig,
+) -> ([u8; 32], [u8; 32]) {
     let hkdf = |initial_key_material: &[u8], salt: &[u8]| -> [u8; 32] {
         let mut engine = HmacEngine::<sha256::Hash>::new(salt);
         engine.input(initial_key_material);
         Hmac::from_engine(engine).to_byte_array()
     };
 
-    let prk = hkdf(vss_seed, b"pseudo_random_key");
-    let k1 = hkdf(&prk, b"data_encryption_key");
-    let k2 = hkdf(&prk, &[&k1[..], b"obfuscation_key"].concat());
+    let prk = hkdf(vss_seed, config.hkdf_prk_salt.as_bytes());
+    let k1 = hkdf(&prk, config.hkdf_data_encryption_salt.as_bytes());
+    let k2 = hkdf(&prk, &[&k1[..], config.hkdf_obfuscation_salt.as_bytes()].concat());
     (k1, k2)
 }
 
+/// HTTP statuses a server uses for endpoints it does not implement. 404 isn't among them:
+/// it is as likely to mean a wrong base URL, and is reported as an unknown error instead.
+const UNSUPPORTED_HTTP_STATUSES: [&str; 2] = ["HttpStatusCode: 405", "HttpStatusCode: 501"];
+
+/// HTTP status a server uses when the client is sending too many requests
+const RATE_LIMITED_HTTP_STATUS: &str = "HttpStatusCode: 429";
+
+/// Start of the HTTP statuses of server-side failures, such as a gateway in front of the
+/// server answering 502 or 503 with a body that isn't an `ErrorResponse`
+const SERVER_ERROR_HTTP_STATUS: &str = "HttpStatusCode: 5";
+
+/// How `vss-client` starts the message of an `InternalError` for a response the server did
+/// send: an error body that isn't an `ErrorResponse`, an unknown error code, or a success
+/// body that isn't valid protobuf (prost's `DecodeError`).
+const SERVER_RESPONSE_ERRORS: [&str; 3] = [
+    "Unable to decode ErrorResponse from server",
+    "VSS responded with an unknown error code",
+    "failed to decode Protobuf message",
+];
+
+/// How reqwest describes a request that ran out of time, and the connect phase of one.
+/// The HTTP client of clients without a header provider enforces `connect_timeout_ms` and
+/// `read_timeout_ms` itself and fails with these in the message.
+const TIMED_OUT_ERROR: &str = "operation timed out";
+const CONNECT_ERROR: &str = "error trying to connect";
+
+/// Whether an `InternalError` message comes from the transport, i.e. reqwest failed to send
+/// the request or to read the response, rather than from a response the server sent.
+fn is_transport_error(msg: &str) -> bool {
+    !msg.contains("HttpStatusCode:")
+        && !SERVER_RESPONSE_ERRORS.iter().any(|prefix| msg.starts_with(prefix))
+}
+
+/// Whether the server rejected a request for exceeding its rate limit.
+///
+/// Like unsupported endpoints, this is only visible in the message of an `InternalError`,
+/// which `vss-client` produces only for error bodies that aren't an `ErrorResponse`. A 429
+/// carrying an `ErrorResponse` is reported by its error code instead.
+fn is_rate_limited(error: &ExternalVssError) -> bool {
+    matches!(error, ExternalVssError::InternalError(msg) if msg.contains(RATE_LIMITED_HTTP_STATUS))
+}
+
+/// Checks whether an undecodable error response carries an "endpoint not implemented" status.
+///
+/// `vss-client` only reports the HTTP status inside the message of an `InternalError` when
+/// the server's error body isn't a VSS `ErrorResponse`, which is how servers lacking an
+/// endpoint respond.
+fn is_unsupported_status(msg: &str) -> bool {
+    UNSUPPORTED_HTTP_STATUSES.iter().any(|status| msg.contains(status))
+}
+
+/// Whether an undecodable error response carries a 5xx status other than "not implemented"
+fn is_server_error_status(msg: &str) -> bool {
+    msg.contains(SERVER_ERROR_HTTP_STATUS) && !is_unsupported_status(msg)
+}
+
+/// Whether a failed request is retried by the client's retry policy.
+///
+/// Must agree with `VssError::is_retryable` for the error `convert_error` produces.
+pub(crate) fn is_retryable_error(error: &ExternalVssError) -> bool {
+    match error {
+        ExternalVssError::InternalServerError(_) => true,
+        ExternalVssError::InternalError(msg) => {
+            is_rate_limited(error)
+                || is_server_error_status(msg)
+                || (is_transport_error(msg) && !msg.contains(PIN_MISMATCH_ERROR))
+        }
+        ExternalVssError::NoSuchKeyError(_)
+        | ExternalVssError::InvalidRequestError(_)
+        | ExternalVssError::ConflictError(_)
+        | ExternalVssError::AuthError(_) => false,
+    }
+}
+
 /// Converts external VSS errors to internal error types.
 ///
 /// # Parameters
-/// - `error`: The external VssError from the vss-client library
+/// - `error`: The external VssError from the vss-client library, optionally with retry metrics
 /// - `operation`: The operation that failed (for context)
 ///
 /// # Returns
 /// Internal VssError with appropriate error details
-fn convert_error(error: ExternalVssError, _operation: &str) -> VssError {
+pub(crate) fn convert_error(error: impl Into<BackendError>, operation: &str) -> VssError {
+    let BackendError { error, attempts, elapsed_ms, timed_out } = error.into();
+    match timed_out {
+        Some(TimedOut::Connect(msg)) => return VssError::ConnectionError { error_details: msg },
+        Some(TimedOut::Read(msg)) => return VssError::TimeoutError { error_details: msg },
+        None => {}
+    }
+    if is_rate_limited(&error) {
+        return VssError::RateLimited { retry_after_ms: None, attempts, elapsed_ms };
+    }
     match error {
+        ExternalVssError::InternalError(msg) if is_unsupported_status(&msg) => {
+            VssError::Unsupported { feature: format!("{} ({})", operation, msg) }
+        }
+        ExternalVssError::InternalError(msg) if msg.contains(PIN_MISMATCH_ERROR) => {
+            VssError::ConnectionError { error_details: msg }
+        }
+        ExternalVssError::InternalError(msg)
+            if is_transport_error(&msg) && msg.contains(TIMED_OUT_ERROR) =>
+        {
+            if msg.contains(CONNECT_ERROR) {
+                VssError::ConnectionError { error_details: msg }
+            } else {
+                VssError::TimeoutError { error_details: msg }
+            }
+        }
+        ExternalVssError::InternalError(msg)
+            if is_transport_error(&msg) || is_server_error_status(&msg) =>
+        {
+            VssError::NetworkError { error_details: msg, attempts, elapsed_ms }
+        }
         ExternalVssError::NoSuchKeyError(msg) => VssError::GetError { error_details: format!("Not found: {}", msg) },
-        ExternalVssError::InternalServerError(msg) => VssError::NetworkError { error_details: msg },
+        ExternalVssError::InternalServerError(msg) => VssError::NetworkError {
+            error_details: msg,
+            attempts,
+            elapsed_ms,
+        },
         ExternalVssError::InvalidRequestError(msg) => VssError::InvalidData { error_details: msg },
         ExternalVssError::InternalError(msg) => VssError::UnknownError { error_details: msg },
-        ExternalVssError::ConflictError(msg) => VssError::StoreError { error_details: format!("Conflict: {}", msg) },
+        ExternalVssError::ConflictError(msg) => VssError::ConflictError {
+            current_version: None,
+            error_details: msg,
+            conflicting_keys: vec![],
+        },
         ExternalVssError::AuthError(msg) => VssError::AuthError { error_details: msg },
     }
 }
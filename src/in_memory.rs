@@ -0,0 +1,223 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use vss_client::error::VssError as ExternalVssError;
+use vss_client::types::{
+    DeleteObjectRequest, DeleteObjectResponse, GetObjectRequest, GetObjectResponse,
+    KeyValue as ExternalKeyValue, ListKeyVersionsRequest, ListKeyVersionsResponse,
+    PutObjectRequest, PutObjectResponse,
+};
+
+/// Number of keys returned per page when a list request doesn't specify a page size.
+const DEFAULT_PAGE_SIZE: usize = 100;
+
+#[derive(Clone)]
+struct StoredObject {
+    version: i64,
+    value: Vec<u8>,
+}
+
+#[derive(Default)]
+struct InMemoryNamespace {
+    objects: BTreeMap<String, StoredObject>,
+    global_version: i64,
+}
+
+/// An in-memory stand-in for a VSS server, following the same request/response contract.
+///
+/// Writes are conditional on `version` unless it is `-1`: a key that doesn't exist yet
+/// must be written with version `0`, an existing key with its current version. Like the
+/// server, a conditional write increments the stored version, while an unconditional
+/// write always stores version 1. Transactions are all-or-nothing.
+#[derive(Default)]
+pub(crate) struct InMemoryStore {
+    namespaces: Mutex<HashMap<String, InMemoryNamespace>>,
+    /// Transactions still accepted before puts start failing, None for no limit
+    remaining_puts: Mutex<Option<usize>>,
+    /// Number of list requests served
+    list_requests: AtomicUsize,
+}
+
+impl InMemoryStore {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Makes every put fail with a server error once `count` more transactions succeeded
+    #[cfg(test)]
+    pub(crate) fn fail_puts_after(&self, count: usize) {
+        *self.remaining_puts.lock().unwrap() = Some(count);
+    }
+
+    /// Returns the number of list requests served so far
+    #[cfg(test)]
+    pub(crate) fn list_requests(&self) -> usize {
+        self.list_requests.load(Ordering::SeqCst)
+    }
+
+    pub(crate) fn get_object(
+        &self,
+        request: &GetObjectRequest,
+    ) -> Result<GetObjectResponse, ExternalVssError> {
+        let namespaces = self.namespaces.lock().unwrap();
+        let object = namespaces
+            .get(&request.store_id)
+            .and_then(|ns| ns.objects.get(&request.key))
+            .ok_or_else(|| ExternalVssError::NoSuchKeyError(request.key.clone()))?;
+
+        Ok(GetObjectResponse {
+            value: Some(ExternalKeyValue {
+                key: request.key.clone(),
+                version: object.version,
+                value: object.value.clone(),
+            }),
+        })
+    }
+
+    pub(crate) fn put_object(
+        &self,
+        request: &PutObjectRequest,
+    ) -> Result<PutObjectResponse, ExternalVssError> {
+        if let Some(remaining) = self.remaining_puts.lock().unwrap().as_mut() {
+            if *remaining == 0 {
+                return Err(ExternalVssError::InternalServerError(
+                    "Injected put failure".to_string(),
+                ));
+            }
+            *remaining -= 1;
+        }
+
+        let mut namespaces = self.namespaces.lock().unwrap();
+        let namespace = namespaces.entry(request.store_id.clone()).or_default();
+
+        if let Some(global_version) = request.global_version {
+            if global_version != namespace.global_version {
+                return Err(ExternalVssError::ConflictError(format!(
+                    "Global version mismatch: expected {}, found {}",
+                    global_version, namespace.global_version
+                )));
+            }
+        }
+
+        for item in request.transaction_items.iter().chain(request.delete_items.iter()) {
+            check_version(namespace, item)?;
+        }
+
+        for item in &request.transaction_items {
+            let version = match item.version {
+                -1 => 1,
+                _ => namespace.objects.get(&item.key).map_or(0, |o| o.version) + 1,
+            };
+            namespace.objects.insert(
+                item.key.clone(),
+                StoredObject {
+                    version,
+                    value: item.value.clone(),
+                },
+            );
+        }
+        for item in &request.delete_items {
+            namespace.objects.remove(&item.key);
+        }
+        if request.global_version.is_some() {
+            namespace.global_version += 1;
+        }
+
+        Ok(PutObjectResponse {})
+    }
+
+    pub(crate) fn delete_object(
+        &self,
+        request: &DeleteObjectRequest,
+    ) -> Result<DeleteObjectResponse, ExternalVssError> {
+        let key_value = request.key_value.as_ref().ok_or_else(|| {
+            ExternalVssError::InvalidRequestError("key_value is required".to_string())
+        })?;
+
+        let mut namespaces = self.namespaces.lock().unwrap();
+        let namespace = namespaces.entry(request.store_id.clone()).or_default();
+
+        // Deleting a missing key is not an error, matching the server's idempotent delete.
+        if namespace.objects.contains_key(&key_value.key) {
+            check_version(namespace, key_value)?;
+            namespace.objects.remove(&key_value.key);
+        }
+
+        Ok(DeleteObjectResponse {})
+    }
+
+    pub(crate) fn list_key_versions(
+        &self,
+        request: &ListKeyVersionsRequest,
+    ) -> Result<ListKeyVersionsResponse, ExternalVssError> {
+        self.list_requests.fetch_add(1, Ordering::SeqCst);
+        let page_size = match request.page_size {
+            Some(size) if size > 0 => size as usize,
+            _ => DEFAULT_PAGE_SIZE,
+        };
+        let key_prefix = request.key_prefix.clone().unwrap_or_default();
+
+        let namespaces = self.namespaces.lock().unwrap();
+        let namespace = match namespaces.get(&request.store_id) {
+            Some(namespace) => namespace,
+            None => {
+                return Ok(ListKeyVersionsResponse {
+                    key_versions: vec![],
+                    next_page_token: None,
+                    global_version: Some(0),
+                })
+            }
+        };
+
+        let mut matching = namespace
+            .objects
+            .iter()
+            .filter(|(key, _)| key.starts_with(&key_prefix))
+            .filter(|(key, _)| match &request.page_token {
+                Some(token) => key.as_str() > token.as_str(),
+                None => true,
+            });
+
+        let key_versions: Vec<ExternalKeyValue> = matching
+            .by_ref()
+            .take(page_size)
+            .map(|(key, object)| ExternalKeyValue {
+                key: key.clone(),
+                version: object.version,
+                value: vec![],
+            })
+            .collect();
+
+        let next_page_token = if matching.next().is_some() {
+            key_versions.last().map(|kv| kv.key.clone())
+        } else {
+            None
+        };
+
+        Ok(ListKeyVersionsResponse {
+            key_versions,
+            next_page_token,
+            global_version: Some(namespace.global_version),
+        })
+    }
+}
+
+/// Rejects a conditional write whose expected version doesn't match the stored one
+fn check_version(
+    namespace: &InMemoryNamespace,
+    item: &ExternalKeyValue,
+) -> Result<(), ExternalVssError> {
+    if item.version == -1 {
+        return Ok(());
+    }
+
+    let current_version = namespace.objects.get(&item.key).map_or(0, |o| o.version);
+    if item.version != current_version {
+        return Err(ExternalVssError::ConflictError(format!(
+            "Version mismatch for key {}: expected {}, found {}",
+            item.key, item.version, current_version
+        )));
+    }
+
+    Ok(())
+}
@@ -1,27 +1,75 @@
 mod errors;
-#[cfg(test)]
+#[cfg(all(test, feature = "global-client"))]
 mod ffi_tests;
 mod implementation;
+#[cfg(any(test, feature = "test-utils"))]
+mod in_memory;
 mod tests;
 mod types;
 
 pub use errors::*;
-pub use implementation::{VssClient, derive_vss_store_id};
+pub use implementation::{
+    decrypt_blob, derivation_info, derive_storage_keys, derive_vss_store_id,
+    derive_vss_store_id_parts, derive_vss_store_id_with_format, RotationError, VssClient,
+    DEFAULT_PAGE_SIZE, DEFAULT_STORE_ID_FORMAT, HASHED_KEY_PREFIX, LARGE_VALUE_CHUNK_BYTES,
+    LARGE_VALUE_MANIFEST_CONTENT_TYPE, RESERVED_KEY_PREFIX, VSS_HARDENED_CHILD_INDEX,
+    VSS_LNURL_AUTH_HARDENED_CHILD_INDEX, VSS_STORE_ID_HARDENED_CHILD_INDEX,
+};
 pub use types::*;
+#[cfg(feature = "test-utils")]
+pub use vss_client::util::storable_builder::EntropySource;
 
-uniffi::setup_scaffolding!();
+// The scaffolding uniffi 0.25 generates compares function pointers when the continuation
+// callback is set, which newer compilers warn about. Lint attributes on a macro invocation
+// are ignored, so the scaffolding lives in a module that allows the lint, re-exported at
+// the crate root where uniffi expects it.
+#[allow(unpredictable_function_pointer_comparisons)]
+mod scaffolding {
+    uniffi::setup_scaffolding!();
+}
+pub use scaffolding::*;
 
-use bip39::Mnemonic;
+use bitcoin::hex::FromHex;
+#[cfg(feature = "global-client")]
+use implementation::{master_seed, parse_mnemonic};
+#[cfg(feature = "global-client")]
 use once_cell::sync::OnceCell;
-use std::str::FromStr;
+#[cfg(feature = "global-client")]
+use std::sync::atomic::{AtomicU64, Ordering};
+#[cfg(feature = "global-client")]
 use std::sync::{Arc, Mutex};
+#[cfg(feature = "global-client")]
 use tokio::runtime::Runtime;
 
+// The global client, its runtime and the `vss_*` functions operating on it make up the
+// `global-client` feature. Without it only the `VssClient` API and the stateless helpers
+// remain, and `VssClient` is Rust-only, so FFI bindings must be built with the feature.
+#[cfg(feature = "global-client")]
 static RUNTIME: OnceCell<Runtime> = OnceCell::new();
+#[cfg(feature = "global-client")]
 static VSS_CLIENT: OnceCell<Arc<Mutex<Option<VssClient>>>> = OnceCell::new();
+#[cfg(feature = "global-client")]
+static CLIENT_GENERATION: AtomicU64 = AtomicU64::new(0);
+#[cfg(feature = "global-client")]
+static IN_FLIGHT: AtomicU64 = AtomicU64::new(0);
+#[cfg(feature = "global-client")]
+static IDLE: tokio::sync::Notify = tokio::sync::Notify::const_new();
+// Set once the thread driving background tasks on a current-thread runtime is started
+#[cfg(feature = "global-client")]
+static BACKGROUND_DRIVER: OnceCell<()> = OnceCell::new();
 
-// Helper macro to handle async execution in both test and production environments
+// Helper macro to handle async execution in both test and production environments.
+// Operations run through it count as in flight for `vss_shutdown_client_graceful`.
+#[cfg(feature = "global-client")]
 macro_rules! execute_async {
+    ($async_block:expr) => {{
+        let _in_flight = InFlightGuard::enter();
+        execute_untracked!($async_block)
+    }};
+}
+
+#[cfg(feature = "global-client")]
+macro_rules! execute_untracked {
     ($async_block:expr) => {{
         if tokio::runtime::Handle::try_current().is_ok() {
             // We're already in an async context (e.g., during tests)
@@ -34,6 +82,41 @@ macro_rules! execute_async {
     }};
 }
 
+/// Counts an operation as in flight until dropped
+#[cfg(feature = "global-client")]
+struct InFlightGuard;
+
+#[cfg(feature = "global-client")]
+impl InFlightGuard {
+    fn enter() -> Self {
+        IN_FLIGHT.fetch_add(1, Ordering::SeqCst);
+        InFlightGuard
+    }
+}
+
+#[cfg(feature = "global-client")]
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        if IN_FLIGHT.fetch_sub(1, Ordering::SeqCst) == 1 {
+            IDLE.notify_waiters();
+        }
+    }
+}
+
+/// Waits until no operation is in flight
+#[cfg(feature = "global-client")]
+async fn wait_until_idle() {
+    loop {
+        // Registered before checking, so a wakeup in between isn't missed
+        let idle = IDLE.notified();
+        if IN_FLIGHT.load(Ordering::SeqCst) == 0 {
+            return;
+        }
+        idle.await;
+    }
+}
+
+#[cfg(feature = "global-client")]
 fn ensure_runtime() -> &'static Runtime {
     RUNTIME.get_or_init(|| {
         tokio::runtime::Builder::new_current_thread()
@@ -43,10 +126,140 @@ fn ensure_runtime() -> &'static Runtime {
     })
 }
 
+/// Configures the Tokio runtime used to drive VSS operations.
+///
+/// By default a current-thread runtime is created lazily on first use. Call this
+/// before creating any client to use a multi-threaded runtime instead, which lets
+/// concurrent operations make progress in parallel.
+///
+/// # Parameters
+/// - `worker_threads`: Number of worker threads for the runtime (must be at least 1)
+///
+/// # Returns
+/// Ok(()) if the runtime was created, or a VssError if the runtime is already
+/// initialized or could not be built.
+///
+/// # Example
+/// ```
+/// vss_init_runtime(4)?;
+/// ```
+#[cfg(feature = "global-client")]
+#[uniffi::export]
+pub fn vss_init_runtime(worker_threads: u32) -> Result<(), VssError> {
+    if worker_threads == 0 {
+        return Err(VssError::InvalidData {
+            error_details: "worker_threads must be at least 1".to_string(),
+        });
+    }
+
+    if RUNTIME.get().is_some() {
+        return Err(VssError::UnknownError {
+            error_details: "Runtime already initialized".to_string(),
+        });
+    }
+
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(worker_threads as usize)
+        .enable_all()
+        .build()
+        .map_err(|e| VssError::UnknownError {
+            error_details: format!("Failed to create Tokio runtime: {}", e),
+        })?;
+
+    RUNTIME.set(runtime).map_err(|_| VssError::UnknownError {
+        error_details: "Runtime already initialized".to_string(),
+    })
+}
+
+/// Runs a background task such as a debounced write to completion.
+///
+/// Outside a Tokio context the task goes to the shared runtime. A current-thread runtime
+/// only makes progress while a call blocks on it, so there a single long-lived thread is
+/// started on first use to drive all background tasks, rather than one thread per task.
+#[cfg(feature = "global-client")]
+fn spawn_background<F>(task: F)
+where
+    F: std::future::Future<Output = ()> + Send + 'static,
+{
+    if let Ok(handle) = tokio::runtime::Handle::try_current() {
+        handle.spawn(task);
+        return;
+    }
+
+    let rt = ensure_runtime();
+    if rt.handle().runtime_flavor() == tokio::runtime::RuntimeFlavor::CurrentThread {
+        BACKGROUND_DRIVER.get_or_init(|| {
+            std::thread::Builder::new()
+                .name("vss-background".to_string())
+                .spawn(move || rt.block_on(std::future::pending::<()>()))
+                .expect("Failed to spawn background thread");
+        });
+    }
+    rt.spawn(task);
+}
+
+#[cfg(feature = "global-client")]
 fn get_vss_client() -> &'static Arc<Mutex<Option<VssClient>>> {
     VSS_CLIENT.get_or_init(|| Arc::new(Mutex::new(None)))
 }
 
+/// Makes `client` the global client in a single step and returns its generation.
+///
+/// The swap happens under the client lock, so `try_get_client` sees either the previous
+/// client or the new one, never `None`. Operations already running keep the clone they
+/// started with and finish against the previous client.
+#[cfg(feature = "global-client")]
+fn install_client(client: VssClient) -> u64 {
+    let storage = get_vss_client();
+    let mut guard = storage.lock().unwrap();
+    *guard = Some(client);
+    CLIENT_GENERATION.fetch_add(1, Ordering::SeqCst) + 1
+}
+
+/// Like `install_client`, but only if no other client was installed since `generation`.
+/// Fails with `ConflictError` otherwise, keeping the client installed meanwhile.
+#[cfg(feature = "global-client")]
+fn install_client_if_current(client: VssClient, generation: u64) -> Result<u64, VssError> {
+    let storage = get_vss_client();
+    let mut guard = storage.lock().unwrap();
+    let current = CLIENT_GENERATION.load(Ordering::SeqCst);
+    if current != generation {
+        return Err(VssError::ConflictError {
+            error_details: format!(
+                "The global client was replaced (generation {} to {}) while the operation ran",
+                generation, current
+            ),
+            current_version: None,
+            conflicting_keys: vec![],
+        });
+    }
+    *guard = Some(client);
+    Ok(CLIENT_GENERATION.fetch_add(1, Ordering::SeqCst) + 1)
+}
+
+/// Clears the global client and returns it. The generation is bumped, so an operation
+/// that would reinstall a client based on the cleared one fails with `ConflictError`.
+#[cfg(feature = "global-client")]
+fn take_client() -> Option<VssClient> {
+    let storage = get_vss_client();
+    let mut guard = storage.lock().unwrap();
+    let client = guard.take();
+    CLIENT_GENERATION.fetch_add(1, Ordering::SeqCst);
+    client
+}
+
+/// Returns the global client together with its generation, read under the same lock
+#[cfg(feature = "global-client")]
+fn try_get_client_with_generation() -> Result<(VssClient, u64), VssError> {
+    let storage = get_vss_client();
+    let guard = storage.lock().unwrap();
+    let client = guard.as_ref().ok_or(VssError::ConnectionError {
+        error_details: "VSS client not initialized. Call vss_new_client() first.".to_string(),
+    })?;
+    Ok((client.clone(), CLIENT_GENERATION.load(Ordering::SeqCst)))
+}
+
+#[cfg(feature = "global-client")]
 fn try_get_client() -> Result<VssClient, VssError> {
     let storage = get_vss_client();
     let guard = storage.lock().unwrap();
@@ -55,7 +268,7 @@ fn try_get_client() -> Result<VssClient, VssError> {
         .ok_or(VssError::ConnectionError {
             error_details: "VSS client not initialized. Call vss_new_client() first.".to_string(),
         })
-        .map(|c| c.clone())
+        .cloned()
 }
 
 /// Creates a new VSS (Versioned Storage Service) client without authentication.
@@ -64,11 +277,13 @@ fn try_get_client() -> Result<VssClient, VssError> {
 /// the global client for subsequent VSS operations.
 ///
 /// # Parameters
-/// - `base_url`: The base URL of the VSS server (e.g., "https://vss.example.com")
+/// - `base_url`: The base URL of the VSS server (e.g., "https://vss.example.com"). Trailing
+///   slashes are removed
 /// - `store_id`: A unique identifier for the storage namespace/keyspace
 ///
 /// # Returns
-/// Ok(()) if the client was created successfully, or a VssError if the client creation fails.
+/// Ok(()) if the client was created successfully, or a VssError if the client creation fails,
+/// e.g. a ConnectionError if `base_url` isn't an http or https URL.
 ///
 /// # Example
 /// ```
@@ -77,312 +292,2519 @@ fn try_get_client() -> Result<VssClient, VssError> {
 ///     "my-app-store".to_string()
 /// ).await?;
 /// ```
+#[cfg(feature = "global-client")]
 #[uniffi::export]
 pub async fn vss_new_client(base_url: String, store_id: String) -> Result<(), VssError> {
-    execute_async!(async move {
-        let client = VssClient::new(base_url, store_id).await?;
-
-        let storage = get_vss_client();
-        let mut guard = storage.lock().unwrap();
-        *guard = Some(client);
-        drop(guard);
-
-        Ok(())
-    })
+    vss_new_client_with_config(base_url, store_id, VssClientConfig::default()).await
 }
 
-/// Creates a new VSS (Versioned Storage Service) client with LNURL-auth using a BIP39 mnemonic.
-///
-/// This function establishes a connection to a VSS server using LNURL-auth
-/// for authentication.
+/// Creates a new VSS client without authentication, using a custom configuration.
 ///
 /// # Parameters
 /// - `base_url`: The base URL of the VSS server
 /// - `store_id`: A unique identifier for the storage namespace/keyspace
-/// - `mnemonic`: BIP39 mnemonic phrase (12 or 24 words)
-/// - `passphrase`: Optional BIP39 passphrase
-/// - `lnurl_auth_server_url`: The LNURL-auth server URL for authentication
+/// - `config`: Client behaviour settings such as the maximum key length
 ///
 /// # Returns
 /// Ok(()) if the client was created successfully, or a VssError if the client creation fails.
 ///
 /// # Example
 /// ```
-/// vss_new_client_with_lnurl_auth(
+/// vss_new_client_with_config(
 ///     "https://vss.example.com".to_string(),
 ///     "my-app-store".to_string(),
-///     "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about".to_string(),
-///     None,
-///     "https://auth.example.com/lnurl".to_string()
+///     VssClientConfig { max_key_length: 256, ..Default::default() }
 /// ).await?;
 /// ```
+#[cfg(feature = "global-client")]
 #[uniffi::export]
-pub async fn vss_new_client_with_lnurl_auth(
+pub async fn vss_new_client_with_config(
     base_url: String,
     store_id: String,
-    mnemonic: String,
-    passphrase: Option<String>,
-    lnurl_auth_server_url: String,
+    config: VssClientConfig,
 ) -> Result<(), VssError> {
     execute_async!(async move {
-        let mnemonic = Mnemonic::from_str(&mnemonic).map_err(|e| VssError::ConnectionError {
-            error_details: format!("Invalid mnemonic: {}", e),
-        })?;
-
-        let seed = match passphrase {
-            Some(passphrase) => mnemonic.to_seed(&passphrase),
-            None => mnemonic.to_seed(""),
-        };
-        let seed_array: [u8; 32] =
-            seed[..32]
-                .try_into()
-                .map_err(|_| VssError::ConnectionError {
-                    error_details: "Failed to extract seed from mnemonic".to_string(),
-                })?;
+        let client = VssClient::new_with_config(base_url, store_id, config).await?;
 
-        let client =
-            VssClient::new_with_lnurl_auth(base_url, store_id, seed_array, lnurl_auth_server_url)
-                .await?;
-
-        let storage = get_vss_client();
-        let mut guard = storage.lock().unwrap();
-        *guard = Some(client);
-        drop(guard);
+        install_client(client);
 
         Ok(())
     })
 }
 
-/// Stores a key-value pair in the VSS server.
+/// Creates a new VSS client without authentication that only trusts pinned certificates.
 ///
-/// This function writes data to the VSS server. The server automatically
-/// manages versioning, incrementing the version number with each update.
+/// The server is accepted if its leaf certificate, or an intermediate of a chain that also
+/// validates against the web PKI roots, matches one of the pins. Any other certificate fails
+/// the connection with `VssError::ConnectionError`, which is not retried.
 ///
 /// # Parameters
-/// - `key`: The unique key identifier for the data
-/// - `value`: The binary data to store
+/// - `base_url`: The base URL of the VSS server, which must use `https`
+/// - `store_id`: A unique identifier for the storage namespace/keyspace
+/// - `pins`: Certificates or SPKI SHA-256 hashes to accept, at least one
+/// - `config`: Client behaviour settings such as the maximum key length
 ///
 /// # Returns
-/// A VssItem containing the stored key, value, and version number,
-/// or a VssError if the operation fails.
+/// Ok(()) if the client was created successfully, or a VssError if the client creation fails.
 ///
 /// # Example
 /// ```
-/// let item = vss_store(
-///     "user-settings".to_string(),
-///     vec![1, 2, 3, 4]
+/// vss_new_client_with_pinned_certificates(
+///     "https://vss.example.com".to_string(),
+///     "my-app-store".to_string(),
+///     vec![CertificatePin::SpkiSha256 { hash: spki_hash }],
+///     VssClientConfig::default()
 /// ).await?;
-/// println!("Stored at version: {}", item.version);
 /// ```
+#[cfg(feature = "global-client")]
 #[uniffi::export]
-pub async fn vss_store(
-    key: String,
-    value: Vec<u8>
-) -> Result<VssItem, VssError> {
+pub async fn vss_new_client_with_pinned_certificates(
+    base_url: String,
+    store_id: String,
+    pins: Vec<CertificatePin>,
+    config: VssClientConfig,
+) -> Result<(), VssError> {
     execute_async!(async move {
-        let client = try_get_client()?;
-        client.store(key, value).await
+        let client =
+            VssClient::new_with_pinned_certificates(base_url, store_id, pins, config).await?;
+
+        install_client(client);
+
+        Ok(())
     })
 }
 
-/// Retrieves a value by key from the VSS server.
+/// Atomically replaces the global client with a new one and returns its generation.
 ///
-/// This function fetches the current version of the data associated with the given key.
-/// Returns None if the key does not exist.
+/// The new client is fully built before the swap, and the swap never leaves a window in
+/// which operations find no client. Operations that started before the swap complete on
+/// the client they started with; operations started afterwards use the new one. If
+/// creating the new client fails, the current client stays in place.
 ///
 /// # Parameters
-/// - `key`: The key to retrieve
+/// - `base_url`: The base URL of the VSS server
+/// - `store_id`: A unique identifier for the storage namespace/keyspace
+/// - `config`: Client behaviour settings such as the maximum key length
 ///
 /// # Returns
-/// An Option containing the VssItem if found, None if the key doesn't exist,
-/// or a VssError if the operation fails.
+/// The generation of the installed client, or a VssError if the client creation fails.
 ///
 /// # Example
 /// ```
-/// match vss_get("user-settings".to_string()).await? {
-///     Some(item) => println!("Found data with version: {}", item.version),
-///     None => println!("Key not found")
-/// }
+/// let generation = vss_replace_client(
+///     "https://vss.example.com".to_string(),
+///     "my-app-store".to_string(),
+///     VssClientConfig::default()
+/// ).await?;
 /// ```
+#[cfg(feature = "global-client")]
 #[uniffi::export]
-pub async fn vss_get(
-    key: String
-) -> Result<Option<VssItem>, VssError> {
+pub async fn vss_replace_client(
+    base_url: String,
+    store_id: String,
+    config: VssClientConfig,
+) -> Result<u64, VssError> {
     execute_async!(async move {
-        let client = try_get_client()?;
-        client.get(key).await
+        let client = VssClient::new_with_config(base_url, store_id, config).await?;
+        Ok(install_client(client))
     })
 }
 
-/// Lists all items in the store, optionally filtered by key prefix.
+/// Atomically replaces the global client with a new LNURL-auth client and returns its
+/// generation.
 ///
-/// This function retrieves both keys and their associated values/versions.
-/// It's useful for browsing stored data but can be expensive for large datasets.
+/// Builds the client like `vss_new_client_with_lnurl_auth_and_config` and swaps it in like
+/// `vss_replace_client`. If creating the new client fails, the current client stays in
+/// place.
 ///
 /// # Parameters
-/// - `prefix`: Optional key prefix filter (e.g., "user/" to get all user keys)
-///             If None or empty, returns all items
+/// - `base_url`: The base URL of the VSS server
+/// - `store_id`: A unique identifier for the storage namespace/keyspace
+/// - `mnemonic`: BIP39 mnemonic phrase (12, 15, 18, 21 or 24 words)
+/// - `passphrase`: Optional BIP39 passphrase
+/// - `lnurl_auth_server_url`: The LNURL-auth server URL for authentication
+/// - `config`: Client behaviour settings such as the maximum key length
 ///
 /// # Returns
-/// A vector of VssItems containing all matching key-value pairs,
-/// or a VssError if the operation fails.
-///
-/// # Example
-/// ```
-/// // List all items with keys starting with "config/"
-/// let items = vss_list(Some("config/".to_string())).await?;
-/// for item in items {
-///     println!("Key: {}, Version: {}", item.key, item.version);
-/// }
-/// ```
+/// The generation of the installed client, or a VssError if the client creation fails.
+#[cfg(feature = "global-client")]
 #[uniffi::export]
-pub async fn vss_list(
-    prefix: Option<String>
-) -> Result<Vec<VssItem>, VssError> {
+pub async fn vss_replace_client_with_lnurl_auth(
+    base_url: String,
+    store_id: String,
+    mnemonic: String,
+    passphrase: Option<String>,
+    lnurl_auth_server_url: String,
+    config: VssClientConfig,
+) -> Result<u64, VssError> {
     execute_async!(async move {
-        let client = try_get_client()?;
-        client.list(prefix).await
+        let seed_array = seed_from_mnemonic(&mnemonic, passphrase)?;
+        let client = VssClient::new_with_lnurl_auth_and_config(
+            base_url,
+            store_id,
+            seed_array,
+            lnurl_auth_server_url,
+            config,
+        )
+        .await?;
+        Ok(install_client(client))
     })
 }
 
-/// Lists keys and their versions without retrieving the actual values.
-///
-/// This function is more efficient than `vss_list` when you only need to know
-/// what keys exist and their versions, without downloading the actual data.
+/// Like `vss_replace_client_with_lnurl_auth`, but takes a raw seed instead of a mnemonic.
 ///
 /// # Parameters
-/// - `prefix`: Optional key prefix filter (e.g., "user/" to get all user keys)
-///             If None or empty, returns all keys
+/// - `base_url`: The base URL of the VSS server
+/// - `store_id`: A unique identifier for the storage namespace/keyspace
+/// - `seed`: The master seed (32 or 64 bytes), as for `vss_new_client_with_lnurl_auth_seed`
+/// - `lnurl_auth_server_url`: The LNURL-auth server URL for authentication
+/// - `config`: Client behaviour settings such as the maximum key length
 ///
 /// # Returns
-/// A vector of KeyVersion structs containing key names and version numbers,
-/// or a VssError if the operation fails.
-///
-/// # Example
-/// ```
-/// // List all keys starting with "temp/"
-/// let keys = vss_list_keys(Some("temp/".to_string())).await?;
-/// for kv in keys {
-///     println!("Key: {} is at version: {}", kv.key, kv.version);
-/// }
-/// ```
+/// The generation of the installed client, or a VssError if the seed has the wrong length
+/// or the client creation fails.
+#[cfg(feature = "global-client")]
 #[uniffi::export]
-pub async fn vss_list_keys(
-    prefix: Option<String>
-) -> Result<Vec<KeyVersion>, VssError> {
+pub async fn vss_replace_client_with_lnurl_auth_seed(
+    base_url: String,
+    store_id: String,
+    seed: Vec<u8>,
+    lnurl_auth_server_url: String,
+    config: VssClientConfig,
+) -> Result<u64, VssError> {
     execute_async!(async move {
-        let client = try_get_client()?;
-        client.list_keys(prefix).await
+        let seed_array = master_seed(&seed)?;
+        let client = VssClient::new_with_lnurl_auth_and_config(
+            base_url,
+            store_id,
+            seed_array,
+            lnurl_auth_server_url,
+            config,
+        )
+        .await?;
+        Ok(install_client(client))
     })
 }
 
-/// Stores multiple key-value pairs in a single atomic transaction.
+/// Atomically replaces the global client with a new certificate-pinned client and returns
+/// its generation.
 ///
-/// This function allows batch storage of multiple items. All items will be
-/// stored together or the entire operation will fail, ensuring data consistency.
+/// Builds the client like `vss_new_client_with_pinned_certificates` and swaps it in like
+/// `vss_replace_client`. If creating the new client fails, the current client stays in
+/// place.
 ///
 /// # Parameters
-/// - `items`: A vector of KeyValue pairs to store
+/// - `base_url`: The base URL of the VSS server, which must use https
+/// - `store_id`: A unique identifier for the storage namespace/keyspace
+/// - `pins`: The accepted certificates or SubjectPublicKeyInfo hashes, at least one
+/// - `config`: Client behaviour settings such as the maximum key length
 ///
 /// # Returns
-/// A vector of VssItems representing the stored data with their assigned versions,
-/// or a VssError if the operation fails.
-///
-/// # Example
-/// ```
-/// let items_to_store = vec![
-///     KeyValue { key: "config/theme".to_string(), value: vec![1, 0] },
-///     KeyValue { key: "config/lang".to_string(), value: vec![2, 0] },
-/// ];
-/// let stored_items = vss_put_with_key_prefix(items_to_store).await?;
-/// println!("Stored {} items", stored_items.len());
-/// ```
+/// The generation of the installed client, or a VssError if the client creation fails.
+#[cfg(feature = "global-client")]
 #[uniffi::export]
-pub async fn vss_put_with_key_prefix(
-    items: Vec<KeyValue>
-) -> Result<Vec<VssItem>, VssError> {
+pub async fn vss_replace_client_with_pinned_certificates(
+    base_url: String,
+    store_id: String,
+    pins: Vec<CertificatePin>,
+    config: VssClientConfig,
+) -> Result<u64, VssError> {
     execute_async!(async move {
-        let client = try_get_client()?;
-        client.put_with_key_prefix(items).await
+        let client =
+            VssClient::new_with_pinned_certificates(base_url, store_id, pins, config).await?;
+        Ok(install_client(client))
     })
 }
 
-/// Deletes a key-value pair from the VSS server.
-///
-/// This function removes the specified key and its associated data from storage.
-/// The operation is idempotent - deleting a non-existent key will not cause an error.
+/// Returns the generation of the current global client.
 ///
-/// # Parameters
-/// - `key`: The key to delete
+/// The generation increases every time a client is installed, whether by creating,
+/// replacing or rotating it, and when `vss_shutdown_client` or
+/// `vss_shutdown_client_graceful` clears it. It is 0 before the first client. Comparing generations
+/// before and after a long operation tells whether the client was swapped meanwhile.
 ///
 /// # Returns
-/// `true` if the key was found and deleted, `false` if the key didn't exist,
-/// or a VssError if the operation fails.
+/// The current generation
 ///
 /// # Example
 /// ```
-/// let was_deleted = vss_delete("temp-data".to_string()).await?;
-/// if was_deleted {
-///     println!("Key was successfully deleted");
-/// } else {
-///     println!("Key did not exist");
+/// let before = vss_client_generation();
+/// let items = vss_list(None).await?;
+/// if vss_client_generation() != before {
+///     println!("Client was replaced during the listing");
 /// }
 /// ```
+#[cfg(feature = "global-client")]
 #[uniffi::export]
-pub async fn vss_delete(
-    key: String
-) -> Result<bool, VssError> {
-    execute_async!(async move {
-        let client = try_get_client()?;
-        client.delete(key).await
-    })
+pub fn vss_client_generation() -> u64 {
+    CLIENT_GENERATION.load(Ordering::SeqCst)
 }
 
-/// Derives a deterministic VSS store ID from a mnemonic and optional passphrase.
-///
-/// This function creates a consistent store ID that can be used across devices for the same wallet.
-/// The store ID is derived using BIP32 key derivation at a specific VSS path, ensuring it's
-/// cryptographically secure and deterministic.
+/// Checks whether a global client is currently installed.
 ///
-/// # Parameters
-/// - `prefix`: A prefix to include in the store ID (e.g., "bitkit_v1_regtest")
-/// - `mnemonic`: BIP39 mnemonic phrase (12 or 24 words)
-/// - `passphrase`: Optional BIP39 passphrase
+/// Lets the host decide, for example, whether to show a login screen without calling
+/// an operation just to catch the "not initialized" error.
 ///
 /// # Returns
-/// A deterministic store ID string that combines the prefix with a derived identifier.
+/// true if a client has been created and not shut down
 ///
 /// # Example
 /// ```
-/// let store_id = vss_derive_store_id(
-///     "bitkit_v1_regtest".to_string(),
-///     "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about".to_string(),
-///     None
-/// )?;
-/// println!("Store ID: {}", store_id);
+/// if !vss_is_initialized() {
+///     vss_new_client(base_url, store_id).await?;
+/// }
 /// ```
+#[cfg(feature = "global-client")]
 #[uniffi::export]
-pub fn vss_derive_store_id(
-    prefix: String,
-    mnemonic: String,
-    passphrase: Option<String>,
-) -> Result<String, VssError> {
-    derive_vss_store_id(prefix, mnemonic, passphrase)
+pub fn vss_is_initialized() -> bool {
+    VSS_CLIENT
+        .get()
+        .is_some_and(|storage| storage.lock().unwrap().is_some())
 }
 
-/// Shuts down the VSS client and clears the global client state.
+/// Creates a VSS client backed by an in-memory store instead of a server.
 ///
-/// This function is optional but recommended for clean shutdown in applications
-/// that want to explicitly release resources.
+/// Intended for host-app tests: the full API works without a network, with versions
+/// incremented on each write. Data is lost when the client is shut down or replaced.
+/// Only available with the `test-utils` feature.
+///
+/// # Parameters
+/// - `store_id`: A unique identifier for the storage namespace/keyspace
+///
+/// # Example
+/// ```
+/// vss_new_in_memory_client("test-store".to_string());
+/// ```
+#[cfg(all(feature = "global-client", feature = "test-utils"))]
+#[uniffi::export]
+pub fn vss_new_in_memory_client(store_id: String) {
+    let client = VssClient::new_in_memory(store_id);
+
+    install_client(client);
+}
+
+/// Creates a new VSS (Versioned Storage Service) client with LNURL-auth using a BIP39 mnemonic.
+///
+/// This function establishes a connection to a VSS server using LNURL-auth
+/// for authentication.
+///
+/// # Parameters
+/// - `base_url`: The base URL of the VSS server
+/// - `store_id`: A unique identifier for the storage namespace/keyspace
+/// - `mnemonic`: BIP39 mnemonic phrase (12, 15, 18, 21 or 24 words)
+/// - `passphrase`: Optional BIP39 passphrase
+/// - `lnurl_auth_server_url`: The LNURL-auth server URL for authentication
+///
+/// # Returns
+/// Ok(()) if the client was created successfully, or a VssError if the client creation fails.
+///
+/// # Example
+/// ```
+/// vss_new_client_with_lnurl_auth(
+///     "https://vss.example.com".to_string(),
+///     "my-app-store".to_string(),
+///     "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about".to_string(),
+///     None,
+///     "https://auth.example.com/lnurl".to_string()
+/// ).await?;
+/// ```
+#[cfg(feature = "global-client")]
+#[uniffi::export]
+pub async fn vss_new_client_with_lnurl_auth(
+    base_url: String,
+    store_id: String,
+    mnemonic: String,
+    passphrase: Option<String>,
+    lnurl_auth_server_url: String,
+) -> Result<(), VssError> {
+    vss_new_client_with_lnurl_auth_and_config(
+        base_url,
+        store_id,
+        mnemonic,
+        passphrase,
+        lnurl_auth_server_url,
+        VssClientConfig::default(),
+    )
+    .await
+}
+
+/// Creates a new VSS client with LNURL-auth from a raw seed instead of a mnemonic.
+///
+/// For integrations that already hold the BIP39 seed or another master secret. A 64-byte
+/// seed is truncated to 32 bytes exactly like the mnemonic variants do, so passing the
+/// BIP39 seed of a phrase yields the same keys and store as passing the phrase itself.
+///
+/// # Parameters
+/// - `base_url`: The base URL of the VSS server
+/// - `store_id`: A unique identifier for the storage namespace/keyspace
+/// - `seed`: The master seed (32 or 64 bytes)
+/// - `lnurl_auth_server_url`: The LNURL-auth server URL for authentication
+///
+/// # Returns
+/// Ok(()) if the client was created successfully, or a VssError if the seed has the
+/// wrong length or the client creation fails.
+///
+/// # Example
+/// ```
+/// vss_new_client_with_lnurl_auth_seed(
+///     "https://vss.example.com".to_string(),
+///     "my-app-store".to_string(),
+///     bip39_seed.to_vec(),
+///     "https://auth.example.com/lnurl".to_string()
+/// ).await?;
+/// ```
+#[cfg(feature = "global-client")]
+#[uniffi::export]
+pub async fn vss_new_client_with_lnurl_auth_seed(
+    base_url: String,
+    store_id: String,
+    seed: Vec<u8>,
+    lnurl_auth_server_url: String,
+) -> Result<(), VssError> {
+    execute_async!(async move {
+        let seed_array = master_seed(&seed)?;
+
+        let client = VssClient::new_with_lnurl_auth(
+            base_url,
+            store_id,
+            seed_array,
+            lnurl_auth_server_url,
+        )
+        .await?;
+
+        install_client(client);
+
+        Ok(())
+    })
+}
+
+/// Creates a new VSS client with LNURL-auth using a BIP39 mnemonic and a custom configuration.
+///
+/// Values are always encrypted. Setting `config.obfuscate_keys` to false stores keys in
+/// plaintext, which helps server-side debugging but lets the server operator read every
+/// key name. Key names often reveal what data is stored, so only turn it off when keys
+/// carry no sensitive information. A store must always be read with the same setting.
+///
+/// # Parameters
+/// - `base_url`: The base URL of the VSS server
+/// - `store_id`: A unique identifier for the storage namespace/keyspace
+/// - `mnemonic`: BIP39 mnemonic phrase (12, 15, 18, 21 or 24 words)
+/// - `passphrase`: Optional BIP39 passphrase
+/// - `lnurl_auth_server_url`: The LNURL-auth server URL for authentication
+/// - `config`: Client behaviour settings such as the maximum key length
+///
+/// # Returns
+/// Ok(()) if the client was created successfully, or a VssError if the client creation fails.
+#[cfg(feature = "global-client")]
+#[uniffi::export]
+pub async fn vss_new_client_with_lnurl_auth_and_config(
+    base_url: String,
+    store_id: String,
+    mnemonic: String,
+    passphrase: Option<String>,
+    lnurl_auth_server_url: String,
+    config: VssClientConfig,
+) -> Result<(), VssError> {
+    execute_async!(async move {
+        let seed_array = seed_from_mnemonic(&mnemonic, passphrase)?;
+
+        let client = VssClient::new_with_lnurl_auth_and_config(
+            base_url,
+            store_id,
+            seed_array,
+            lnurl_auth_server_url,
+            config,
+        )
+        .await?;
+
+        install_client(client);
+
+        Ok(())
+    })
+}
+
+/// Derives the 32-byte master seed of a BIP39 mnemonic and optional passphrase
+#[cfg(feature = "global-client")]
+fn seed_from_mnemonic(mnemonic: &str, passphrase: Option<String>) -> Result<[u8; 32], VssError> {
+    let mnemonic = parse_mnemonic(mnemonic)?;
+    let seed = mnemonic.to_seed(passphrase.as_deref().unwrap_or(""));
+    master_seed(&seed)
+}
+
+/// Reports whether the current client encrypts values.
+///
+/// Clients created with LNURL-auth derive encryption and obfuscation keys from the
+/// seed and return `true`, whether or not `obfuscate_keys` is enabled. Clients created
+/// with `vss_new_client` store values under an all-zero key with plaintext keys and
+/// return `false`. Reading a store written in one mode with a client in the other mode
+/// will fail.
+///
+/// # Returns
+/// Whether encryption is active, or a VssError if no client is initialized.
+///
+/// # Example
+/// ```
+/// if !vss_is_encrypted()? {
+///     println!("Warning: store contents are not encrypted");
+/// }
+/// ```
+#[cfg(feature = "global-client")]
+#[uniffi::export]
+pub fn vss_is_encrypted() -> Result<bool, VssError> {
+    let client = try_get_client()?;
+    Ok(client.is_encrypted())
+}
+
+/// Returns the storage key the current client uses on the server for a key.
+///
+/// Useful when inspecting the server's raw store to diagnose sync mismatches. With
+/// encryption enabled this is the obfuscated key; otherwise it is the key itself.
+/// Nothing is sent to the server.
+///
+/// # Parameters
+/// - `key`: The key as passed to the other functions
+///
+/// # Returns
+/// The storage key, or a VssError if the key is invalid or no client is initialized.
+///
+/// # Example
+/// ```
+/// let storage_key = vss_obfuscated_key("user-settings".to_string())?;
+/// println!("Stored on the server as {}", storage_key);
+/// ```
+#[cfg(feature = "global-client")]
+#[uniffi::export]
+pub fn vss_obfuscated_key(key: String) -> Result<String, VssError> {
+    let client = try_get_client()?;
+    client.obfuscated_key(&key)
+}
+
+/// Checks that the current client's key obfuscator round-trips a sample key.
+///
+/// Obfuscates a sample key and deobfuscates it again, without contacting the server.
+/// Keys that can't be deobfuscated make listings fail, so this can be run at startup to
+/// catch a broken obfuscator early. It does nothing when keys aren't obfuscated.
+///
+/// # Returns
+/// Ok(()) if the sample key comes back unchanged, or a ConnectionError if it doesn't or
+/// no client is initialized.
+///
+/// # Example
+/// ```
+/// vss_verify_key_obfuscation()?;
+/// ```
+#[cfg(feature = "global-client")]
+#[uniffi::export]
+pub fn vss_verify_key_obfuscation() -> Result<(), VssError> {
+    let client = try_get_client()?;
+    client.verify_key_obfuscation()
+}
+
+/// Returns the key prefix the current client sends to the server when listing a prefix.
+///
+/// Obfuscated keys don't share the prefixes of the keys they hide, so the server can't
+/// filter them by prefix. In that case this returns an empty string and listings fetch
+/// the whole key list and filter it on the client. Nothing is sent to the server.
+///
+/// # Parameters
+/// - `prefix`: The key prefix as passed to the list functions
+///
+/// # Returns
+/// The prefix sent to the server, empty if the server doesn't filter, or a VssError if
+/// no client is initialized.
+///
+/// # Example
+/// ```
+/// if vss_resolve_storage_prefix("contacts/".to_string())?.is_empty() {
+///     println!("Listing contacts/ scans the whole store");
+/// }
+/// ```
+#[cfg(feature = "global-client")]
+#[uniffi::export]
+pub fn vss_resolve_storage_prefix(prefix: String) -> Result<String, VssError> {
+    let client = try_get_client()?;
+    Ok(client.resolve_storage_prefix(&prefix))
+}
+
+/// Discovers what the connected VSS server supports.
+///
+/// VSS servers don't expose a version or capabilities endpoint, so this infers what it
+/// can from a minimal listing request. Fields that can't be determined are None, in which
+/// case callers should stay with conservative defaults.
+///
+/// # Returns
+/// The server's ServerInfo, or a VssError if the server can't be reached.
+///
+/// # Example
+/// ```
+/// let info = vss_server_info().await?;
+/// if info.supports_global_version {
+///     println!("Server tracks a store-wide version");
+/// }
+/// ```
+#[cfg(feature = "global-client")]
+#[uniffi::export]
+pub async fn vss_server_info() -> Result<ServerInfo, VssError> {
+    execute_async!(async move {
+        let client = try_get_client()?;
+        client.server_info().await
+    })
+}
+
+/// Checks whether the current store holds at least one key.
+///
+/// Meant for onboarding: a store ID derived with a mistyped prefix points at an empty
+/// namespace instead of failing, so an unexpectedly empty store suggests checking the
+/// store ID before treating missing data as a sync failure. Lists a single key.
+///
+/// # Returns
+/// true if the store has any keys, or a VssError if the server can't be reached.
+///
+/// # Example
+/// ```
+/// if !vss_verify_store_nonempty().await? {
+///     println!("This store appears empty. Is the store ID correct?");
+/// }
+/// ```
+#[cfg(feature = "global-client")]
+#[uniffi::export]
+pub async fn vss_verify_store_nonempty() -> Result<bool, VssError> {
+    execute_async!(async move {
+        let client = try_get_client()?;
+        client.verify_store_nonempty().await
+    })
+}
+
+/// Makes sure the current LNURL-auth client holds a valid JWT, minting one if needed.
+///
+/// Call it when the app comes to the foreground so the next user-triggered operation
+/// doesn't pay for the LNURL-auth round trips. A token still valid for more than a minute
+/// is kept, so calling it often is cheap. Does nothing for clients without LNURL-auth.
+///
+/// # Returns
+/// Ok(()) once a valid token is cached, VssError::AuthError if the auth server rejects
+/// the client, or VssError::ConnectionError if it can't be reached.
+///
+/// # Example
+/// ```
+/// // On resume
+/// vss_refresh_auth().await?;
+/// ```
+#[cfg(feature = "global-client")]
+#[uniffi::export]
+pub async fn vss_refresh_auth() -> Result<(), VssError> {
+    execute_async!(async move {
+        let client = try_get_client()?;
+        client.refresh_auth().await
+    })
+}
+
+/// Sets a listener told about each retry of a request to the server, e.g. to show
+/// "retrying (3/10)" instead of a frozen spinner during a slow sync.
+///
+/// The listener belongs to the current client: a client created later starts without
+/// one. Requests that succeed on their first attempt never call it.
+///
+/// # Parameters
+/// - `listener`: The listener to call, None to remove the current one
+///
+/// # Returns
+/// Ok(()) once set, or a ConnectionError if no client is initialized.
+///
+/// # Example
+/// ```
+/// vss_set_retry_listener(Some(Box::new(RetryBanner::new())))?;
+/// ```
+#[cfg(feature = "global-client")]
+#[uniffi::export]
+pub fn vss_set_retry_listener(listener: Option<Box<dyn VssRetryListener>>) -> Result<(), VssError> {
+    let client = try_get_client()?;
+    client.set_retry_listener(listener.map(Arc::from));
+    Ok(())
+}
+
+/// Stores a key-value pair in the VSS server.
+///
+/// This function writes data to the VSS server. The server automatically
+/// manages versioning, incrementing the version number with each update.
+///
+/// # Parameters
+/// - `key`: The unique key identifier for the data
+/// - `value`: The binary data to store
+///
+/// # Returns
+/// A VssItem containing the stored key, value, and version number,
+/// or a VssError if the operation fails.
+///
+/// # Example
+/// ```
+/// let item = vss_store(
+///     "user-settings".to_string(),
+///     vec![1, 2, 3, 4]
+/// ).await?;
+/// println!("Stored at version: {}", item.version);
+/// ```
+#[cfg(feature = "global-client")]
+#[uniffi::export]
+pub async fn vss_store(
+    key: String,
+    value: Vec<u8>
+) -> Result<VssItem, VssError> {
+    execute_async!(async move {
+        let client = try_get_client()?;
+        client.store(key, value).await
+    })
+}
+
+/// Stores a UTF-8 string under a key.
+///
+/// Convenience for text values such as JSON; equivalent to `vss_store` with the
+/// string's bytes. Read it back with `vss_item_value_as_string`.
+///
+/// # Parameters
+/// - `key`: The unique key identifier for the data
+/// - `value`: The text to store
+///
+/// # Returns
+/// A VssItem containing the stored key, value, and version number,
+/// or a VssError if the operation fails.
+///
+/// # Example
+/// ```
+/// vss_store_string("profile".to_string(), r#"{"name":"satoshi"}"#.to_string()).await?;
+/// ```
+#[cfg(feature = "global-client")]
+#[uniffi::export]
+pub async fn vss_store_string(
+    key: String,
+    value: String
+) -> Result<VssItem, VssError> {
+    execute_async!(async move {
+        let client = try_get_client()?;
+        client.store(key, value.into_bytes()).await
+    })
+}
+
+/// Stores a key-value pair, reporting whether the key was created or updated.
+///
+/// Useful for telling "Created" from "Saved" in the UI. The server doesn't say whether a
+/// write created a key, so the value is first written create-only and, if the key
+/// already exists, stored again unconditionally. Updates therefore take an extra round
+/// trip compared to `vss_store`.
+///
+/// # Parameters
+/// - `key`: The unique key identifier for the data
+/// - `value`: The binary data to store
+///
+/// # Returns
+/// A StoreOutcome with the stored item and whether the key was created,
+/// or a VssError if the operation fails.
+///
+/// # Example
+/// ```
+/// let outcome = vss_store_detailed("contacts/alice".to_string(), data).await?;
+/// let message = if outcome.created { "Created" } else { "Saved" };
+/// ```
+#[cfg(feature = "global-client")]
+#[uniffi::export]
+pub async fn vss_store_detailed(
+    key: String,
+    value: Vec<u8>
+) -> Result<StoreOutcome, VssError> {
+    execute_async!(async move {
+        let client = try_get_client()?;
+        client.store_detailed(key, value).await
+    })
+}
+
+/// Stores a value together with a content type describing how to interpret it.
+///
+/// The content type is encrypted along with the value and comes back as
+/// `VssItem.content_type` on reads. Items stored without one read back with None.
+/// Writes that don't take a content type, such as `vss_store` or `vss_append`, clear it.
+///
+/// # Parameters
+/// - `key`: The unique key identifier for the data
+/// - `value`: The binary data to store
+/// - `content_type`: e.g. "application/json", at most 255 bytes
+///
+/// # Returns
+/// A VssItem containing the stored key, value, version and content type,
+/// or a VssError if the operation fails.
+///
+/// # Example
+/// ```
+/// vss_store_with_metadata(
+///     "profile".to_string(),
+///     br#"{"name":"satoshi"}"#.to_vec(),
+///     Some("application/json".to_string())
+/// ).await?;
+/// ```
+#[cfg(feature = "global-client")]
+#[uniffi::export]
+pub async fn vss_store_with_metadata(
+    key: String,
+    value: Vec<u8>,
+    content_type: Option<String>
+) -> Result<VssItem, VssError> {
+    execute_async!(async move {
+        let client = try_get_client()?;
+        client.store_with_metadata(key, value, content_type).await
+    })
+}
+
+/// Stores a large value in chunks, encrypting and uploading one chunk at a time.
+///
+/// The value is split into 512 KiB chunks stored under `<key>/chunk/<generation>/0`,
+/// `<key>/chunk/<generation>/1`, and so on, where the generation is picked anew for each
+/// write. A small manifest naming the generation is stored at `key` once all chunks are
+/// written, and the previous value's chunks are deleted after that. Don't write your own
+/// keys under `<key>/chunk/`. Read the value back with `vss_get_large`.
+///
+/// # Parameters
+/// - `key`: The key to store the value under
+/// - `value`: The binary data to store
+///
+/// # Returns
+/// The stored manifest item, or a VssError if a write fails. A failure midway leaves
+/// the previous value readable.
+///
+/// # Example
+/// ```
+/// vss_store_large("monitors/archive".to_string(), archive).await?;
+/// ```
+#[cfg(feature = "global-client")]
+#[uniffi::export]
+pub async fn vss_store_large(key: String, value: Vec<u8>) -> Result<VssItem, VssError> {
+    execute_async!(async move {
+        let client = try_get_client()?;
+        client.store_large(key, value).await
+    })
+}
+
+/// Retrieves a value stored with `vss_store_large`, reassembling its chunks.
+///
+/// # Parameters
+/// - `key`: The key the value was stored under
+///
+/// # Returns
+/// An Option containing the VssItem with the whole value if found, None if the key
+/// doesn't exist, or a VssError if the operation fails. Fails with
+/// `VssError::IntegrityError` if a chunk is missing or from another write. A value
+/// stored at `key` without chunking is returned as is.
+///
+/// # Example
+/// ```
+/// let archive = vss_get_large("monitors/archive".to_string()).await?;
+/// ```
+#[cfg(feature = "global-client")]
+#[uniffi::export]
+pub async fn vss_get_large(key: String) -> Result<Option<VssItem>, VssError> {
+    execute_async!(async move {
+        let client = try_get_client()?;
+        client.get_large(key).await
+    })
+}
+
+/// Stores a value only if the key doesn't exist yet.
+///
+/// A create-only write for initializing defaults exactly once across devices: the
+/// server accepts it only for a new key, and an existing value is never overwritten.
+///
+/// # Parameters
+/// - `key`: The unique key identifier for the data
+/// - `value`: The binary data to store
+///
+/// # Returns
+/// true if the key was created, false if it already existed,
+/// or a VssError if the operation fails.
+///
+/// # Example
+/// ```
+/// if vss_store_if_absent("config".to_string(), default_config).await? {
+///     println!("Initialized config");
+/// }
+/// ```
+#[cfg(feature = "global-client")]
+#[uniffi::export]
+pub async fn vss_store_if_absent(
+    key: String,
+    value: Vec<u8>
+) -> Result<bool, VssError> {
+    execute_async!(async move {
+        let client = try_get_client()?;
+        client.store_if_absent(key, value).await
+    })
+}
+
+/// Appends bytes to the value stored under a key, creating the key if needed.
+///
+/// This is a client-side read-modify-write, not a server-side append: the current
+/// value is fetched, `data` is appended, and the result is written back conditionally
+/// on the version that was read. If a concurrent writer wins, the cycle is retried once
+/// before a `VssError::ConflictError` is returned. The full value is transferred in
+/// both directions.
+///
+/// # Parameters
+/// - `key`: The key to append to
+/// - `data`: The bytes to append
+///
+/// # Returns
+/// A VssItem containing the full new value and its version,
+/// or a VssError if the operation fails.
+///
+/// # Example
+/// ```
+/// let item = vss_append("events".to_string(), b"event-42\n".to_vec()).await?;
+/// println!("Log is now {} bytes", item.value.len());
+/// ```
+#[cfg(feature = "global-client")]
+#[uniffi::export]
+pub async fn vss_append(
+    key: String,
+    data: Vec<u8>
+) -> Result<VssItem, VssError> {
+    execute_async!(async move {
+        let client = try_get_client()?;
+        client.append(key, data).await
+    })
+}
+
+/// Replaces the value stored under a key with one computed by the host app.
+///
+/// The generic read-modify-write behind `vss_append`: the current value is fetched and
+/// passed to `updater`, and its result is written back conditionally on the version that
+/// was read. If a concurrent writer wins, the cycle is repeated with the newer value, up
+/// to 5 attempts in total, before a `VssError::ConflictError` is returned. `updater` is
+/// called once per attempt, so it should be free of side effects.
+///
+/// # Parameters
+/// - `key`: The key to update
+/// - `updater`: Computes the new value from the current one, None if the key doesn't exist
+///
+/// # Returns
+/// A VssItem containing the new value and its version,
+/// or a VssError if the operation fails.
+///
+/// # Example
+/// ```
+/// struct Increment;
+/// impl ValueUpdater for Increment {
+///     fn update(&self, current: Option<Vec<u8>>) -> Vec<u8> {
+///         let count = current.map_or(0, |v| v[0]);
+///         vec![count + 1]
+///     }
+/// }
+/// let item = vss_update("counter".to_string(), Box::new(Increment)).await?;
+/// ```
+#[cfg(feature = "global-client")]
+#[uniffi::export]
+pub async fn vss_update(
+    key: String,
+    updater: Box<dyn ValueUpdater>
+) -> Result<VssItem, VssError> {
+    execute_async!(async move {
+        let client = try_get_client()?;
+        client.update(key, |current| updater.update(current)).await
+    })
+}
+
+/// Sets a key to a new value only if its current value matches exactly.
+///
+/// A compare-and-swap on the decrypted value, usable for simple lock or lease patterns.
+/// The write is conditional on the version that was read, so a concurrent change in
+/// between makes the swap fail rather than overwrite it.
+///
+/// # Parameters
+/// - `key`: The key to swap
+/// - `new_value`: The value to write
+/// - `expected_value`: The value the key must currently hold, or None if it must not exist
+///
+/// # Returns
+/// true if the new value was written, false if the current value didn't match,
+/// or a VssError if the read or write fails.
+///
+/// # Example
+/// ```
+/// let acquired = vss_swap("lock".to_string(), b"device-a".to_vec(), None).await?;
+/// ```
+#[cfg(feature = "global-client")]
+#[uniffi::export]
+pub async fn vss_swap(
+    key: String,
+    new_value: Vec<u8>,
+    expected_value: Option<Vec<u8>>
+) -> Result<bool, VssError> {
+    execute_async!(async move {
+        let client = try_get_client()?;
+        client.swap(key, new_value, expected_value).await
+    })
+}
+
+/// Increments a key's version without changing its value.
+///
+/// Meant for lease or heartbeat patterns, where a version bump signals liveness to
+/// other devices. VSS servers have no metadata-only update, so the stored (encrypted)
+/// object is read and written back as-is, conditionally on the version read: the value
+/// is not decrypted, but it is transferred in both directions.
+///
+/// # Parameters
+/// - `key`: The key to touch
+///
+/// # Returns
+/// The key's new version, a GetError if the key doesn't exist, a ConflictError if it
+/// changed between the read and the write, or another VssError if the operation fails.
+///
+/// # Example
+/// ```
+/// let version = vss_touch("device-lease".to_string()).await?;
+/// println!("Lease renewed at version {}", version);
+/// ```
+#[cfg(feature = "global-client")]
+#[uniffi::export]
+pub async fn vss_touch(
+    key: String
+) -> Result<i64, VssError> {
+    execute_async!(async move {
+        let client = try_get_client()?;
+        client.touch(key).await
+    })
+}
+
+/// Retrieves a value by key from the VSS server.
+///
+/// This function fetches the current version of the data associated with the given key.
+/// Returns None if the key does not exist.
+///
+/// # Parameters
+/// - `key`: The key to retrieve
+///
+/// # Returns
+/// An Option containing the VssItem if found, None if the key doesn't exist,
+/// or a VssError if the operation fails.
+///
+/// # Example
+/// ```
+/// match vss_get("user-settings".to_string()).await? {
+///     Some(item) => println!("Found data with version: {}", item.version),
+///     None => println!("Key not found")
+/// }
+/// ```
+#[cfg(feature = "global-client")]
+#[uniffi::export]
+pub async fn vss_get(
+    key: String
+) -> Result<Option<VssItem>, VssError> {
+    execute_async!(async move {
+        let client = try_get_client()?;
+        client.get(key).await
+    })
+}
+
+/// Retrieves an item by key, reusing a recent earlier read to save round trips.
+///
+/// A value read by this function within the last `max_age_ms` milliseconds is returned
+/// without contacting the server. An older one is revalidated with a cheap version check
+/// and only downloaded again if the version changed. Values at version 1, which is where
+/// `vss_store` leaves every key on VSS servers, can't be revalidated this way and are
+/// downloaded again. Writes through this client are seen immediately; changes by other
+/// devices within `max_age_ms`.
+///
+/// # Parameters
+/// - `key`: The key to retrieve
+/// - `max_age_ms`: How old a cached value may be before it is revalidated
+///
+/// # Returns
+/// Some(VssItem) if the key exists, None if it doesn't, or a VssError if the operation fails.
+///
+/// # Example
+/// ```
+/// // Read at most 30 seconds stale
+/// if let Some(item) = vss_get_cached("settings/theme".to_string(), 30_000).await? {
+///     apply_theme(&item.value);
+/// }
+/// ```
+#[cfg(feature = "global-client")]
+#[uniffi::export]
+pub async fn vss_get_cached(key: String, max_age_ms: u64) -> Result<Option<VssItem>, VssError> {
+    execute_async!(async move {
+        let client = try_get_client()?;
+        client.get_cached(key, max_age_ms).await
+    })
+}
+
+/// Retrieves an item by key, or a default if the key doesn't exist.
+///
+/// Nothing is written: a missing key yields an item holding `default` at version 0.
+///
+/// # Parameters
+/// - `key`: The key to retrieve
+/// - `default`: The value to return if the key doesn't exist
+///
+/// # Returns
+/// The stored VssItem, or a version 0 VssItem holding `default`,
+/// or a VssError if the read fails.
+///
+/// # Example
+/// ```
+/// let theme = vss_get_or_default("settings/theme".to_string(), b"light".to_vec()).await?;
+/// ```
+#[cfg(feature = "global-client")]
+#[uniffi::export]
+pub async fn vss_get_or_default(
+    key: String,
+    default: Vec<u8>
+) -> Result<VssItem, VssError> {
+    execute_async!(async move {
+        let client = try_get_client()?;
+        client.get_or_default(key, default).await
+    })
+}
+
+/// Retrieves an item by key, storing a default first if the key doesn't exist.
+///
+/// The default is written create-only, like `vss_store_if_absent`. If another device
+/// creates the key at the same time, its value wins and is returned instead, so the
+/// result is always what the store holds.
+///
+/// # Parameters
+/// - `key`: The key to retrieve
+/// - `default`: The value to store if the key doesn't exist
+///
+/// # Returns
+/// The VssItem that ended up stored under the key, or a VssError if the operation fails.
+///
+/// # Example
+/// ```
+/// let device_id = vss_get_or_store_default("device-id".to_string(), new_id).await?;
+/// ```
+#[cfg(feature = "global-client")]
+#[uniffi::export]
+pub async fn vss_get_or_store_default(
+    key: String,
+    default: Vec<u8>
+) -> Result<VssItem, VssError> {
+    execute_async!(async move {
+        let client = try_get_client()?;
+        client.get_or_store_default(key, default).await
+    })
+}
+
+/// Retrieves a value by key, making sure a preceding write by this client is visible.
+///
+/// VSS servers offer no read consistency controls, so the client remembers its last
+/// write to each key and re-reads, with bounded retries, until the server returns that
+/// write or a newer one. Use it right after `vss_store` or `vss_delete` when the backend
+/// may be eventually consistent. Keys this client hasn't written are read once.
+///
+/// # Parameters
+/// - `key`: The key to retrieve
+///
+/// # Returns
+/// An Option containing the VssItem if found, None if the key doesn't exist,
+/// or a VssError if the operation fails. If the write is still not visible once the
+/// retries are exhausted, the server's current answer is returned.
+///
+/// # Example
+/// ```
+/// vss_store("user-settings".to_string(), data).await?;
+/// let item = vss_get_consistent("user-settings".to_string()).await?;
+/// ```
+#[cfg(feature = "global-client")]
+#[uniffi::export]
+pub async fn vss_get_consistent(
+    key: String
+) -> Result<Option<VssItem>, VssError> {
+    execute_async!(async move {
+        let client = try_get_client()?;
+        client.get_consistent(key).await
+    })
+}
+
+/// Waits until a key reaches at least the given version, then retrieves it.
+///
+/// The key's version is polled with a listing request, with the delay between checks
+/// doubling from 50ms up to 2s, and the value is fetched once the threshold is met. Useful
+/// for coordinating devices, e.g. waiting for another device to take over a lease. VSS
+/// servers reset a key to version 1 on unconditional writes, so the writing side should
+/// use `vss_put_conditional` or `vss_update` for the version to keep growing.
+///
+/// # Parameters
+/// - `key`: The key to wait for
+/// - `min_version`: The lowest version to accept
+/// - `timeout_ms`: How long to wait, in milliseconds. 0 checks once without waiting
+///
+/// # Returns
+/// The VssItem once the key is at `min_version` or later, None if the timeout elapses
+/// first, or a VssError if a request fails
+///
+/// # Example
+/// ```
+/// if let Some(item) = vss_wait_for_version("lease".to_string(), 3, 10_000).await? {
+///     println!("Lease taken over at version {}", item.version);
+/// }
+/// ```
+#[cfg(feature = "global-client")]
+#[uniffi::export]
+pub async fn vss_wait_for_version(
+    key: String,
+    min_version: i64,
+    timeout_ms: u64,
+) -> Result<Option<VssItem>, VssError> {
+    execute_async!(async move {
+        let client = try_get_client()?;
+        client.wait_for_version(key, min_version, timeout_ms).await
+    })
+}
+
+/// Reads part of a stored value, e.g. the header of a large blob.
+///
+/// Values are encrypted as a whole, and VSS servers don't support range reads, so the
+/// full value is downloaded and decrypted and only the requested bytes are passed back
+/// across the FFI. This doesn't reduce network transfer.
+///
+/// # Parameters
+/// - `key`: The key to read
+/// - `offset`: Index of the first byte to return
+/// - `length`: Maximum number of bytes to return
+///
+/// # Returns
+/// The requested bytes (fewer if the value is shorter, empty if `offset` is past its end),
+/// None if the key doesn't exist, or a VssError if the operation fails.
+///
+/// # Example
+/// ```
+/// let header = vss_get_range("backup".to_string(), 0, 64).await?;
+/// ```
+#[cfg(feature = "global-client")]
+#[uniffi::export]
+pub async fn vss_get_range(
+    key: String,
+    offset: u64,
+    length: u64
+) -> Result<Option<Vec<u8>>, VssError> {
+    execute_async!(async move {
+        let client = try_get_client()?;
+        client.get_range(key, offset, length).await
+    })
+}
+
+/// Retrieves a key at a specific version.
+///
+/// VSS servers only keep the latest version of each key. This returns the item when
+/// `version` matches the current version, and fails with `VssError::Unsupported` for
+/// any older (or newer) version instead of silently returning the latest value.
+///
+/// # Parameters
+/// - `key`: The key to retrieve
+/// - `version`: The version to read
+///
+/// # Returns
+/// The VssItem if the key is at `version`, None if the key doesn't exist,
+/// or a VssError if the version is not available or the operation fails.
+///
+/// # Example
+/// ```
+/// match vss_get_version_at("user-settings".to_string(), 3).await {
+///     Ok(Some(item)) => println!("Still at version {}", item.version),
+///     Ok(None) => println!("Key not found"),
+///     Err(VssError::Unsupported { .. }) => println!("Version 3 is no longer available"),
+///     Err(e) => return Err(e),
+/// }
+/// ```
+#[cfg(feature = "global-client")]
+#[uniffi::export]
+pub async fn vss_get_version_at(
+    key: String,
+    version: i64,
+) -> Result<Option<VssItem>, VssError> {
+    execute_async!(async move {
+        let client = try_get_client()?;
+        client.get_version_at(key, version).await
+    })
+}
+
+/// Retrieves several keys at once without failing on individual keys.
+///
+/// Keys are fetched concurrently. A key that can't be read, for example because its
+/// value fails to decrypt, is reported in `failures` while the rest are still returned,
+/// so one corrupt item doesn't block a sync. Keys that don't exist are omitted.
+///
+/// # Parameters
+/// - `keys`: The keys to retrieve
+/// - `cancel`: Optional handle that stops the read with `VssError::Cancelled` once cancelled
+///
+/// # Returns
+/// A BatchGetResult with the items found, in the order requested, and the per-key
+/// failures, or a VssError if no client is initialized.
+///
+/// # Example
+/// ```
+/// let result = vss_get_many_partial(vec!["a".to_string(), "b".to_string()], None).await?;
+/// for failure in result.failures {
+///     println!("Could not read {}: {}", failure.key, failure.error);
+/// }
+/// ```
+#[cfg(feature = "global-client")]
+#[uniffi::export]
+pub async fn vss_get_many_partial(
+    keys: Vec<String>,
+    cancel: Option<Arc<VssCancelHandle>>
+) -> Result<BatchGetResult, VssError> {
+    execute_async!(async move {
+        let client = try_get_client()?;
+        client.get_many_partial(keys, cancel).await
+    })
+}
+
+/// Checks which of the given keys exist, without downloading any values.
+///
+/// Each key is looked up with a listing request scoped to that exact key, with several
+/// lookups in flight at once. Useful for sync reconciliation over many candidate keys.
+///
+/// # Parameters
+/// - `keys`: The keys to check
+///
+/// # Returns
+/// One flag per key, in the same order, `true` where the key exists,
+/// or a VssError if a key is invalid or a lookup fails.
+///
+/// # Example
+/// ```
+/// let keys = vec!["a".to_string(), "b".to_string()];
+/// let present = vss_exists_many(keys.clone()).await?;
+/// let missing: Vec<_> = keys.iter().zip(present).filter(|(_, p)| !p).collect();
+/// ```
+#[cfg(feature = "global-client")]
+#[uniffi::export]
+pub async fn vss_exists_many(
+    keys: Vec<String>
+) -> Result<Vec<bool>, VssError> {
+    execute_async!(async move {
+        let client = try_get_client()?;
+        client.exists_many(keys).await
+    })
+}
+
+/// Returns the stored size of a value without decrypting it.
+///
+/// The size is that of the encrypted envelope as held by the server, which is what
+/// counts towards storage usage. The VSS listing API does not report sizes, so this
+/// performs a fetch of the stored object.
+///
+/// # Parameters
+/// - `key`: The key to inspect
+///
+/// # Returns
+/// The size in bytes if the key exists, None if it doesn't,
+/// or a VssError if the operation fails.
+///
+/// # Example
+/// ```
+/// if let Some(size) = vss_item_size("user-settings".to_string()).await? {
+///     println!("Stored size: {} bytes", size);
+/// }
+/// ```
+#[cfg(feature = "global-client")]
+#[uniffi::export]
+pub async fn vss_item_size(
+    key: String
+) -> Result<Option<u64>, VssError> {
+    execute_async!(async move {
+        let client = try_get_client()?;
+        client.item_size(key).await
+    })
+}
+
+/// Lists all items in the store, optionally filtered by key prefix.
+///
+/// This function retrieves both keys and their associated values/versions.
+/// It's useful for browsing stored data but can be expensive for large datasets.
+///
+/// # Parameters
+/// - `prefix`: Optional key prefix filter (e.g., "user/" to get all user keys)
+///   If None or empty, returns all items
+///
+/// # Returns
+/// A vector of VssItems containing all matching key-value pairs, or a VssError if the
+/// operation fails, including when any single item can't be read (see `vss_list_partial`).
+/// Keys deleted while listing are left out.
+///
+/// # Example
+/// ```
+/// // List all items with keys starting with "config/"
+/// let items = vss_list(Some("config/".to_string())).await?;
+/// for item in items {
+///     println!("Key: {}, Version: {}", item.key, item.version);
+/// }
+/// ```
+#[cfg(feature = "global-client")]
+#[uniffi::export]
+pub async fn vss_list(
+    prefix: Option<String>
+) -> Result<Vec<VssItem>, VssError> {
+    execute_async!(async move {
+        let client = try_get_client()?;
+        client.list(prefix).await
+    })
+}
+
+/// Retrieves all items under a prefix as a map from key to value.
+///
+/// The natural shape for loading configuration: keys are listed and their values
+/// fetched concurrently, like `vss_list`, without versions or content types.
+///
+/// # Parameters
+/// - `prefix`: Optional key prefix filter. If None or empty, returns all items
+///
+/// # Returns
+/// A map from each matching key to its value, or a VssError if the listing fails or
+/// any value can't be read.
+///
+/// # Example
+/// ```
+/// let channels = vss_get_map(Some("channels/".to_string())).await?;
+/// if let Some(value) = channels.get("channels/main") {
+///     println!("Main channel is {} bytes", value.len());
+/// }
+/// ```
+#[cfg(feature = "global-client")]
+#[uniffi::export]
+pub async fn vss_get_map(
+    prefix: Option<String>,
+) -> Result<std::collections::HashMap<String, Vec<u8>>, VssError> {
+    execute_async!(async move {
+        let client = try_get_client()?;
+        client.get_map(prefix).await
+    })
+}
+
+/// Lists all items like `vss_list`, reporting items that can't be read instead of failing.
+///
+/// A sync engine can use this to carry on with the readable items while surfacing the
+/// ones that failed, e.g. values that don't decrypt with this client's keys.
+///
+/// # Parameters
+/// - `prefix`: Optional key prefix filter. If None or empty, lists all items
+///
+/// # Returns
+/// A BatchGetResult with the items read and the keys that failed with their errors,
+/// or a VssError if the key listing itself fails.
+///
+/// # Example
+/// ```
+/// let result = vss_list_partial(Some("config/".to_string())).await?;
+/// for failure in result.failures {
+///     println!("Could not read {}: {}", failure.key, failure.error);
+/// }
+/// ```
+#[cfg(feature = "global-client")]
+#[uniffi::export]
+pub async fn vss_list_partial(prefix: Option<String>) -> Result<BatchGetResult, VssError> {
+    execute_async!(async move {
+        let client = try_get_client()?;
+        client.list_partial(prefix).await
+    })
+}
+
+/// Returns aggregate statistics for the store, e.g. for a settings or debug screen.
+///
+/// All keys under the prefix are listed, and each key's stored size is fetched with
+/// concurrent raw reads, without decrypting anything. Expect one request per key.
+///
+/// # Parameters
+/// - `prefix`: Optional key prefix filter
+///
+/// # Returns
+/// StoreStats with the key count, total encrypted size and highest version,
+/// or a VssError if the operation fails.
+///
+/// # Example
+/// ```
+/// let stats = vss_store_stats(None).await?;
+/// println!("{} keys, {} bytes", stats.key_count, stats.total_bytes);
+/// ```
+#[cfg(feature = "global-client")]
+#[uniffi::export]
+pub async fn vss_store_stats(
+    prefix: Option<String>
+) -> Result<StoreStats, VssError> {
+    execute_async!(async move {
+        let client = try_get_client()?;
+        client.store_stats(prefix).await
+    })
+}
+
+/// Lists at most `limit` items, optionally filtered by key prefix.
+///
+/// For "first N items" views: listing stops once `limit` items are gathered, so the
+/// rest of the keyspace is never enumerated and only the returned values are fetched.
+/// The items are picked in the server's listing order, which with obfuscated keys is
+/// unrelated to the user keys, so they are then an unspecified selection rather than the
+/// first `limit` keys. Only the returned items are sorted.
+///
+/// # Parameters
+/// - `prefix`: Optional key prefix filter
+/// - `limit`: Maximum number of items to return
+///
+/// # Returns
+/// Up to `limit` items sorted by key, or a VssError if the operation fails.
+///
+/// # Example
+/// ```
+/// let recent = vss_list_limited(Some("notes/".to_string()), 50).await?;
+/// ```
+#[cfg(feature = "global-client")]
+#[uniffi::export]
+pub async fn vss_list_limited(
+    prefix: Option<String>,
+    limit: u32
+) -> Result<Vec<VssItem>, VssError> {
+    execute_async!(async move {
+        let client = try_get_client()?;
+        client.list_limited(prefix, limit).await
+    })
+}
+
+/// Lists one page of items, optionally filtered by key prefix.
+///
+/// Only the values for the keys on the requested page are fetched, concurrently, so
+/// memory use stays bounded by the page size regardless of the store size.
+///
+/// # Parameters
+/// - `prefix`: Optional key prefix filter
+/// - `page_size`: Optional maximum number of items per page (server default if None)
+/// - `page_token`: Token returned with the previous page, None for the first page
+///
+/// # Returns
+/// A ListPage containing the page's items and the token for the next page
+/// (None on the last page), or a VssError if the operation fails.
+///
+/// # Example
+/// ```
+/// let mut page_token = None;
+/// loop {
+///     let page = vss_list_page(Some("config/".to_string()), Some(50), page_token).await?;
+///     for item in page.items {
+///         println!("Key: {}, Version: {}", item.key, item.version);
+///     }
+///     match page.next_page_token {
+///         Some(token) => page_token = Some(token),
+///         None => break,
+///     }
+/// }
+/// ```
+#[cfg(feature = "global-client")]
+#[uniffi::export]
+pub async fn vss_list_page(
+    prefix: Option<String>,
+    page_size: Option<u32>,
+    page_token: Option<String>,
+) -> Result<ListPage, VssError> {
+    execute_async!(async move {
+        let client = try_get_client()?;
+        client.list_page(prefix, page_size, page_token).await
+    })
+}
+
+/// Lists keys and their versions without retrieving the actual values.
+///
+/// This function is more efficient than `vss_list` when you only need to know
+/// what keys exist and their versions, without downloading the actual data.
+/// Keys are sorted lexicographically by their UTF-8 bytes, regardless of the
+/// order the server returns them in.
+///
+/// # Parameters
+/// - `prefix`: Optional key prefix filter (e.g., "user/" to get all user keys)
+///   If None or empty, returns all keys
+///
+/// # Returns
+/// A vector of KeyVersion structs containing key names and version numbers,
+/// sorted by key, or a VssError if the operation fails.
+///
+/// # Example
+/// ```
+/// // List all keys starting with "temp/"
+/// let keys = vss_list_keys(Some("temp/".to_string())).await?;
+/// for kv in keys {
+///     println!("Key: {} is at version: {}", kv.key, kv.version);
+/// }
+/// ```
+#[cfg(feature = "global-client")]
+#[uniffi::export]
+pub async fn vss_list_keys(
+    prefix: Option<String>
+) -> Result<Vec<KeyVersion>, VssError> {
+    execute_async!(async move {
+        let client = try_get_client()?;
+        client.list_keys(prefix).await
+    })
+}
+
+/// Lists the keys under a prefix, without versions or values.
+///
+/// The simplest way to enumerate keys. It pages through the full listing like
+/// `vss_list_keys`, which costs the same since the server always reports versions.
+///
+/// # Parameters
+/// - `prefix`: Optional key prefix filter (e.g., "user/" to get all user keys)
+///
+/// # Returns
+/// The matching keys, sorted like `vss_list_keys`, or a VssError if the
+/// operation fails.
+///
+/// # Example
+/// ```
+/// let keys = vss_list_key_names(Some("contacts/".to_string())).await?;
+/// println!("{} contacts", keys.len());
+/// ```
+#[cfg(feature = "global-client")]
+#[uniffi::export]
+pub async fn vss_list_key_names(prefix: Option<String>) -> Result<Vec<String>, VssError> {
+    execute_async!(async move {
+        let client = try_get_client()?;
+        client.list_key_names(prefix).await
+    })
+}
+
+/// Lists one page of keys and their versions without retrieving values.
+///
+/// The building block for custom paging: pass the returned `next_page_token` back to
+/// fetch the following page, until it is None.
+///
+/// # Parameters
+/// - `prefix`: Optional key prefix filter
+/// - `page_size`: Optional maximum number of keys per page (server default if None)
+/// - `page_token`: Token from the previous page, None for the first page
+///
+/// # Returns
+/// A ListKeyVersionsResponse with the page's keys and versions and the token for
+/// the next page, or a VssError if the operation fails.
+///
+/// # Example
+/// ```
+/// let mut page_token = None;
+/// loop {
+///     let page = vss_list_keys_page(None, Some(100), page_token).await?;
+///     for kv in page.key_versions {
+///         println!("Key: {} is at version: {}", kv.key, kv.version);
+///     }
+///     match page.next_page_token {
+///         Some(token) => page_token = Some(token),
+///         None => break,
+///     }
+/// }
+/// ```
+#[cfg(feature = "global-client")]
+#[uniffi::export]
+pub async fn vss_list_keys_page(
+    prefix: Option<String>,
+    page_size: Option<u32>,
+    page_token: Option<String>,
+) -> Result<ListKeyVersionsResponse, VssError> {
+    execute_async!(async move {
+        let client = try_get_client()?;
+        client.list_keys_page(prefix, page_size, page_token).await
+    })
+}
+
+/// A cursor over the keys under a prefix, fetching one page per call.
+///
+/// The cursor holds the prefix and the current page token itself, so callers don't have
+/// to thread tokens across the FFI boundary. It keeps the client it was created with, so
+/// paging continues against that client even if the global client is replaced.
+#[cfg(feature = "global-client")]
+#[derive(uniffi::Object)]
+pub struct VssKeyCursor {
+    client: VssClient,
+    prefix: Option<String>,
+    page_size: Option<u32>,
+    state: tokio::sync::Mutex<KeyCursorState>,
+}
+
+#[cfg(feature = "global-client")]
+#[derive(Default)]
+struct KeyCursorState {
+    page_token: Option<String>,
+    finished: bool,
+}
+
+#[cfg(feature = "global-client")]
+#[uniffi::export]
+impl VssKeyCursor {
+    /// Fetches the next non-empty page of keys and their versions.
+    ///
+    /// Pages the prefix filter left empty are skipped, so an empty result means the
+    /// listing is exhausted. A failed call leaves the cursor where it was, so it can be
+    /// retried.
+    ///
+    /// # Returns
+    /// The next keys and versions, an empty vector once all keys were returned, or a
+    /// VssError if the operation fails.
+    pub async fn next_page(&self) -> Result<Vec<KeyVersion>, VssError> {
+        execute_async!(async move {
+            let mut state = self.state.lock().await;
+            while !state.finished {
+                let page = self
+                    .client
+                    .list_keys_page(self.prefix.clone(), self.page_size, state.page_token.clone())
+                    .await?;
+                state.finished = page.next_page_token.is_none();
+                state.page_token = page.next_page_token;
+                if !page.key_versions.is_empty() {
+                    return Ok(page.key_versions);
+                }
+            }
+            Ok(Vec::new())
+        })
+    }
+}
+
+/// Creates a cursor over the keys under a prefix.
+///
+/// # Parameters
+/// - `prefix`: Optional key prefix filter
+/// - `page_size`: Optional maximum number of keys per page (server default if None)
+///
+/// # Returns
+/// A VssKeyCursor bound to the current client, or a VssError if no client is initialized.
+///
+/// # Example
+/// ```
+/// let cursor = vss_key_cursor(Some("user/".to_string()), Some(100))?;
+/// loop {
+///     let keys = cursor.next_page().await?;
+///     if keys.is_empty() {
+///         break;
+///     }
+///     for kv in keys {
+///         println!("Key: {} is at version: {}", kv.key, kv.version);
+///     }
+/// }
+/// ```
+#[cfg(feature = "global-client")]
+#[uniffi::export]
+pub fn vss_key_cursor(
+    prefix: Option<String>,
+    page_size: Option<u32>,
+) -> Result<Arc<VssKeyCursor>, VssError> {
+    let client = try_get_client()?;
+    Ok(Arc::new(VssKeyCursor {
+        client,
+        prefix,
+        page_size,
+        state: tokio::sync::Mutex::new(KeyCursorState::default()),
+    }))
+}
+
+/// Stores a key-value pair once no other debounced write to the key followed for a while.
+///
+/// Meant for bursts of writes to the same key, e.g. while a slider is dragged: each call
+/// replaces the key's pending value and restarts its delay, so only the last value is
+/// written. The write happens in the background; a failed write stays pending and is
+/// reported by the next `vss_flush`. Reads don't see pending values, and pending values
+/// are lost if the process exits first, so call `vss_flush` before suspending.
+///
+/// # Parameters
+/// - `key`: The unique key identifier
+/// - `value`: The binary data to store
+/// - `debounce_ms`: How long the key must go without further writes before it is stored
+///
+/// # Returns
+/// Ok(()) once the write is scheduled, or a VssError if the key is invalid or no client is
+/// initialized.
+///
+/// # Example
+/// ```
+/// // Called on every slider change; only the final volume is written
+/// vss_store_debounced("settings/volume".to_string(), vec![volume], 500)?;
+/// ```
+#[cfg(feature = "global-client")]
+#[uniffi::export]
+pub fn vss_store_debounced(key: String, value: Vec<u8>, debounce_ms: u64) -> Result<(), VssError> {
+    let client = try_get_client()?;
+    let write = client.schedule_debounced(key, value, debounce_ms)?;
+    spawn_background(write);
+    Ok(())
+}
+
+/// Writes all values pending from `vss_store_debounced` now.
+///
+/// # Returns
+/// The number of values written, or a VssError for the first write that failed. Values not
+/// written stay pending.
+///
+/// # Example
+/// ```
+/// vss_flush().await?;
+/// ```
+#[cfg(feature = "global-client")]
+#[uniffi::export]
+pub async fn vss_flush() -> Result<u32, VssError> {
+    execute_async!(async move {
+        let client = try_get_client()?;
+        client.flush().await
+    })
+}
+
+/// Stores multiple key-value pairs in atomic transactions.
+///
+/// This function allows batch storage of multiple items. Items are sent in chunks that
+/// respect the client's `max_batch_items` and `max_batch_bytes` limits, each written as
+/// its own transaction: within a chunk all items are stored together or not at all, but
+/// a failure can leave earlier chunks written. Keep the batch within those limits when
+/// the whole set must be atomic: a batch that fits is a single transaction, so an error
+/// means nothing was written. `vss_put_with_key_prefix_partial` reports which chunks were.
+/// A batch whose total encoded size exceeds the client's `max_request_bytes` fails with
+/// `VssError::InvalidData` before anything is sent.
+///
+/// Despite the name, keys are stored as given; use `vss_put_all_with_prefix` to have a
+/// prefix prepended to each key.
+///
+/// # Parameters
+/// - `items`: A vector of KeyValue pairs to store
+///
+/// # Returns
+/// A vector of VssItems representing the stored data with their assigned versions,
+/// or a VssError if the operation fails.
+///
+/// # Example
+/// ```
+/// let items_to_store = vec![
+///     KeyValue { key: "config/theme".to_string(), value: vec![1, 0] },
+///     KeyValue { key: "config/lang".to_string(), value: vec![2, 0] },
+/// ];
+/// let stored_items = vss_put_with_key_prefix(items_to_store).await?;
+/// println!("Stored {} items", stored_items.len());
+/// ```
+#[cfg(feature = "global-client")]
+#[uniffi::export]
+pub async fn vss_put_with_key_prefix(
+    items: Vec<KeyValue>
+) -> Result<Vec<VssItem>, VssError> {
+    execute_async!(async move {
+        let client = try_get_client()?;
+        client.put_with_key_prefix(items).await
+    })
+}
+
+/// Stores multiple key-value pairs like `vss_put_with_key_prefix`, reporting how far the
+/// write got if a chunk fails.
+///
+/// Chunks are written in order and the first failure stops the batch, so `written` holds
+/// exactly the items of the chunks before `failed_chunk_index`. A batch within the
+/// client's batch limits is one chunk, so it is either written in full or not at all.
+///
+/// # Parameters
+/// - `items`: A vector of KeyValue pairs to store
+///
+/// # Returns
+/// A BatchPutResult with the written items and the failed chunk, if any, or a VssError
+/// if a key is invalid, in which case nothing is written.
+///
+/// # Example
+/// ```
+/// let result = vss_put_with_key_prefix_partial(items).await?;
+/// if let Some(index) = result.failed_chunk_index {
+///     println!("Wrote {} items, chunk {} failed", result.written.len(), index);
+/// }
+/// ```
+#[cfg(feature = "global-client")]
+#[uniffi::export]
+pub async fn vss_put_with_key_prefix_partial(
+    items: Vec<KeyValue>,
+) -> Result<BatchPutResult, VssError> {
+    execute_async!(async move {
+        let client = try_get_client()?;
+        client.put_with_key_prefix_partial(items).await
+    })
+}
+
+/// Stores multiple key-value pairs under a common key prefix.
+///
+/// Prepends `prefix` to each item's key, before obfuscation, and stores the items like
+/// `vss_put_with_key_prefix`, with the same batching.
+///
+/// # Parameters
+/// - `prefix`: Prefix to prepend to every key (e.g., "config/")
+/// - `items`: A vector of KeyValue pairs with keys relative to `prefix`
+///
+/// # Returns
+/// The stored VssItems under their full prefixed keys, or a VssError if the operation fails.
+///
+/// # Example
+/// ```
+/// let items = vec![
+///     KeyValue { key: "theme".to_string(), value: vec![1, 0] },
+///     KeyValue { key: "lang".to_string(), value: vec![2, 0] },
+/// ];
+/// // Stores "config/theme" and "config/lang"
+/// vss_put_all_with_prefix("config/".to_string(), items).await?;
+/// ```
+#[cfg(feature = "global-client")]
+#[uniffi::export]
+pub async fn vss_put_all_with_prefix(
+    prefix: String,
+    items: Vec<KeyValue>,
+) -> Result<Vec<VssItem>, VssError> {
+    execute_async!(async move {
+        let client = try_get_client()?;
+        client.put_all_with_prefix(prefix, items).await
+    })
+}
+
+/// Writes several keys in one transaction, each only if it is at its expected version.
+///
+/// For multi-key state transitions: either every item is written or, if any key has
+/// changed since it was read, none is and a `VssError::ConflictError` lists the
+/// mismatched keys in `conflicting_keys`. Finding them takes one extra read per item
+/// after a conflict. The batch is never split, so it must fit the client's
+/// `max_batch_items` and `max_batch_bytes` limits.
+///
+/// # Parameters
+/// - `items`: The keys to write with their values and expected versions: 0 for a key
+///   that must not exist yet, -1 to write the key unconditionally
+///
+/// # Returns
+/// The written items with their new versions, or a VssError if the write fails.
+///
+/// # Example
+/// ```
+/// let items = vec![
+///     VersionedKeyValue { key: "balance".to_string(), value: new_balance, expected_version: 4 },
+///     VersionedKeyValue { key: "ledger/42".to_string(), value: entry, expected_version: 0 },
+/// ];
+/// vss_put_conditional(items).await?;
+/// ```
+#[cfg(feature = "global-client")]
+#[uniffi::export]
+pub async fn vss_put_conditional(
+    items: Vec<VersionedKeyValue>
+) -> Result<Vec<VssItem>, VssError> {
+    execute_async!(async move {
+        let client = try_get_client()?;
+        client.put_conditional(items).await
+    })
+}
+
+/// Exports the items under a prefix as newline-delimited JSON.
+///
+/// One JSON object per line, `{"key":...,"value_base64":...,"version":...}`, ordered by
+/// key so exports can be inspected and diffed. Values are decrypted and base64-encoded,
+/// so the export holds the store contents in plaintext and should be handled as such.
+///
+/// # Parameters
+/// - `prefix`: Optional prefix to export only matching keys
+/// - `progress`: Optional receiver of the number of items read out of the number of keys
+///   to export, reported once the keys are listed and then as items are read
+///
+/// # Returns
+/// The export text, or a VssError if an item can't be read.
+///
+/// # Example
+/// ```
+/// let export = vss_export_ndjson(Some("settings/".to_string()), None).await?;
+/// ```
+#[cfg(feature = "global-client")]
+#[uniffi::export]
+pub async fn vss_export_ndjson(
+    prefix: Option<String>,
+    progress: Option<Box<dyn VssProgress>>,
+) -> Result<String, VssError> {
+    execute_async!(async move {
+        let client = try_get_client()?;
+        client.export_ndjson(prefix, progress.as_deref()).await
+    })
+}
+
+/// Imports items from newline-delimited JSON produced by `vss_export_ndjson`.
+///
+/// The whole input is parsed before anything is written, so malformed input fails with
+/// `VssError::InvalidData` naming the offending line and writes nothing. Items are then
+/// stored like `vss_put_with_key_prefix`, overwriting existing keys; the exported
+/// versions are ignored.
+///
+/// # Parameters
+/// - `ndjson`: The export text
+/// - `progress`: Optional receiver of the number of items written out of the number of
+///   items in the export, reported once the input is parsed and after each batch
+///
+/// # Returns
+/// The number of items written, or a VssError if the input is invalid or a write fails.
+///
+/// # Example
+/// ```
+/// let imported = vss_import_ndjson(export, Some(Box::new(progress_bar))).await?;
+/// ```
+#[cfg(feature = "global-client")]
+#[uniffi::export]
+pub async fn vss_import_ndjson(
+    ndjson: String,
+    progress: Option<Box<dyn VssProgress>>,
+) -> Result<u32, VssError> {
+    execute_async!(async move {
+        let client = try_get_client()?;
+        client.import_ndjson(&ndjson, progress.as_deref()).await
+    })
+}
+
+/// Deletes a key-value pair from the VSS server.
+///
+/// This function removes the specified key and its associated data from storage.
+/// The operation is idempotent - deleting a non-existent key will not cause an error.
+///
+/// # Parameters
+/// - `key`: The key to delete
+///
+/// # Returns
+/// `true` if the key was found and deleted, `false` if the key didn't exist,
+/// or a VssError if the operation fails.
+///
+/// # Example
+/// ```
+/// let was_deleted = vss_delete("temp-data".to_string()).await?;
+/// if was_deleted {
+///     println!("Key was successfully deleted");
+/// } else {
+///     println!("Key did not exist");
+/// }
+/// ```
+#[cfg(feature = "global-client")]
+#[uniffi::export]
+pub async fn vss_delete(
+    key: String
+) -> Result<bool, VssError> {
+    execute_async!(async move {
+        let client = try_get_client()?;
+        client.delete(key).await
+    })
+}
+
+/// Renames a key atomically.
+///
+/// Writes the value under `new_key` and deletes `old_key` in one transaction, so the old
+/// and new states never coexist. The encrypted value is moved unchanged.
+///
+/// # Parameters
+/// - `old_key`: The key to rename
+/// - `new_key`: The new name
+/// - `overwrite`: Replace `new_key` if it already exists
+///
+/// # Returns
+/// true if renamed, false if `old_key` didn't exist, or a VssError if the operation fails:
+/// ConflictError if `new_key` exists without `overwrite`, or if `old_key` changed meanwhile.
+///
+/// # Example
+/// ```
+/// vss_rename("draft/1".to_string(), "published/1".to_string(), false).await?;
+/// ```
+#[cfg(feature = "global-client")]
+#[uniffi::export]
+pub async fn vss_rename(
+    old_key: String,
+    new_key: String,
+    overwrite: bool
+) -> Result<bool, VssError> {
+    execute_async!(async move {
+        let client = try_get_client()?;
+        client.rename(old_key, new_key, overwrite).await
+    })
+}
+
+/// Deletes a key and returns the item it held.
+///
+/// Useful for undo: the item is read, then deleted only if it is still at the version
+/// read, so the returned value is exactly what was removed. A key deleted or rewritten
+/// concurrently is left alone.
+///
+/// # Parameters
+/// - `key`: The key to delete
+///
+/// # Returns
+/// The deleted item, None if the key didn't exist or was deleted or rewritten
+/// concurrently, or a VssError if the operation fails.
+///
+/// # Example
+/// ```
+/// if let Some(removed) = vss_delete_returning("draft".to_string()).await? {
+///     // Keep `removed` around to restore it on undo
+/// }
+/// ```
+#[cfg(feature = "global-client")]
+#[uniffi::export]
+pub async fn vss_delete_returning(
+    key: String
+) -> Result<Option<VssItem>, VssError> {
+    execute_async!(async move {
+        let client = try_get_client()?;
+        client.delete_returning(key).await
+    })
+}
+
+/// Deletes every item in the current store.
+///
+/// Intended for "log out and wipe" flows. All keys are listed, page by page, and deleted
+/// in batches, which works the same whether or not keys are obfuscated. The `confirm`
+/// flag must be set so the wipe can't be triggered by accident. A dry run lists the keys
+/// that would be deleted, e.g. for a confirmation dialog, without deleting anything.
+///
+/// # Parameters
+/// - `confirm`: Must be `true` unless `dry_run` is set; otherwise nothing is deleted and
+///   InvalidData is returned
+/// - `dry_run`: Only list the keys that would be deleted
+/// - `cancel`: Optional handle that stops the wipe with `VssError::Cancelled` once cancelled
+///
+/// # Returns
+/// The keys deleted (or that would be, on a dry run), or a VssError if the operation fails.
+/// A failure or cancellation midway leaves the items deleted so far removed.
+///
+/// # Example
+/// ```
+/// let affected = vss_clear_store(false, true, None).await?;
+/// // ...after the user confirms
+/// let cancel = VssCancelHandle::new();
+/// let deleted = vss_clear_store(true, false, Some(cancel.clone())).await?;
+/// println!("Wiped {} items", deleted.len());
+/// ```
+#[cfg(feature = "global-client")]
+#[uniffi::export]
+pub async fn vss_clear_store(
+    confirm: bool,
+    dry_run: bool,
+    cancel: Option<Arc<VssCancelHandle>>
+) -> Result<Vec<String>, VssError> {
+    execute_async!(async move {
+        let client = try_get_client()?;
+        client.clear_store(confirm, dry_run, cancel).await
+    })
+}
+
+/// Deletes the keys matching a pattern with `*` wildcards.
+///
+/// `*` matches any run of characters, including none and `/`, so
+/// `state/device-*/channels` deletes that key for every device. Matching happens on the
+/// client: every key under the text before the first `*` is listed, and with encryption
+/// enabled every key in the store, so the cost grows with the store size. Matches are
+/// deleted unconditionally in batches. A pattern of only `*` is rejected; use
+/// `vss_clear_store` for that.
+///
+/// # Parameters
+/// - `pattern`: The pattern keys must match in full
+///
+/// # Returns
+/// The deleted keys, or a VssError if listing or a delete fails. Batches deleted before a
+/// failure stay deleted.
+///
+/// # Example
+/// ```
+/// let deleted = vss_delete_matching("state/device-*/channels".to_string()).await?;
+/// println!("Deleted {} keys", deleted.len());
+/// ```
+#[cfg(feature = "global-client")]
+#[uniffi::export]
+pub async fn vss_delete_matching(pattern: String) -> Result<Vec<String>, VssError> {
+    execute_async!(async move {
+        let client = try_get_client()?;
+        client.delete_matching(pattern).await
+    })
+}
+
+/// Deletes keys under a prefix that haven't been written since a known version.
+///
+/// For incremental garbage collection when re-syncing: keys under `prefix` at
+/// `max_version` or below are deleted, newer ones are kept. Deletes are conditional on
+/// the listed version, so a key rewritten while this runs is not deleted.
+///
+/// # Parameters
+/// - `prefix`: Only keys starting with this prefix are considered
+/// - `max_version`: Keys at this version or below are deleted
+/// - `cancel`: Optional handle that stops the deletion with `VssError::Cancelled` once
+///   cancelled. Keys deleted before that stay deleted.
+///
+/// # Returns
+/// The number of keys deleted, or a VssError if the operation fails.
+///
+/// # Example
+/// ```
+/// let removed =
+///     vss_delete_prefix_older_than("cache/".to_string(), last_sync_version, None).await?;
+/// ```
+#[cfg(feature = "global-client")]
+#[uniffi::export]
+pub async fn vss_delete_prefix_older_than(
+    prefix: String,
+    max_version: i64,
+    cancel: Option<Arc<VssCancelHandle>>
+) -> Result<u32, VssError> {
+    execute_async!(async move {
+        let client = try_get_client()?;
+        client.delete_prefix_older_than(prefix, max_version, cancel).await
+    })
+}
+
+/// Re-encrypts the whole store under keys derived from a new seed.
+///
+/// Every item is read with the current encryption and obfuscation keys and written back
+/// under keys derived from `new_seed_hex`, deleting the old storage key in the same
+/// transaction. On success the global client switches to the new keys. The
+/// authentication identity of the client is not changed.
+///
+/// A rotation that fails midway can be resumed by calling this again with the same seed:
+/// items already under the new keys are skipped. If the global client was replaced while
+/// rotating, the replacement is kept and a `VssError::ConflictError` is returned, even
+/// though the store was rotated.
+///
+/// # Parameters
+/// - `new_seed_hex`: Hex-encoded 32-byte seed to derive the new keys from
+///
+/// # Returns
+/// The number of items re-encrypted by this call, or a VssError if the rotation fails.
+///
+/// # Example
+/// ```
+/// let rotated = vss_rotate_encryption(new_seed_hex).await?;
+/// println!("Re-encrypted {} items", rotated);
+/// ```
+#[cfg(feature = "global-client")]
+#[uniffi::export]
+pub async fn vss_rotate_encryption(new_seed_hex: String) -> Result<u32, VssError> {
+    execute_async!(async move {
+        let new_seed = <[u8; 32]>::from_hex(&new_seed_hex).map_err(|e| VssError::InvalidData {
+            error_details: format!("Invalid seed hex: {}", e),
+        })?;
+
+        let (mut client, generation) = try_get_client_with_generation()?;
+        let rotated = client.rotate_encryption(new_seed).await?;
+
+        install_client_if_current(client, generation)?;
+
+        Ok(rotated)
+    })
+}
+
+/// Derives a deterministic VSS store ID from a mnemonic and optional passphrase.
+///
+/// This function creates a consistent store ID that can be used across devices for the same wallet.
+/// The store ID is derived using BIP32 key derivation at a specific VSS path, ensuring it's
+/// cryptographically secure and deterministic.
+///
+/// # Parameters
+/// - `prefix`: A prefix to include in the store ID (e.g., "bitkit_v1_regtest")
+/// - `mnemonic`: BIP39 mnemonic phrase (12, 15, 18, 21 or 24 words)
+/// - `passphrase`: Optional BIP39 passphrase
+///
+/// # Returns
+/// A deterministic store ID string that combines the prefix with a derived identifier.
+///
+/// # Example
+/// ```
+/// let store_id = vss_derive_store_id(
+///     "bitkit_v1_regtest".to_string(),
+///     "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about".to_string(),
+///     None
+/// )?;
+/// println!("Store ID: {}", store_id);
+/// ```
+#[uniffi::export]
+pub fn vss_derive_store_id(
+    prefix: String,
+    mnemonic: String,
+    passphrase: Option<String>,
+) -> Result<String, VssError> {
+    derive_vss_store_id(prefix, mnemonic, passphrase)
+}
+
+/// Derives a store ID like `vss_derive_store_id`, laid out by a template.
+///
+/// `vss_derive_store_id` uses the template `"{prefix}_{id}"`, so passing it here gives the
+/// same store ID. Other templates let different app versions use their own layout.
+///
+/// # Parameters
+/// - `prefix`: A prefix to include in the store ID (e.g., "bitkit_v1_regtest")
+/// - `mnemonic`: BIP39 mnemonic phrase (12, 15, 18, 21 or 24 words)
+/// - `passphrase`: Optional BIP39 passphrase
+/// - `format`: Template in which `{prefix}` and `{id}` are replaced with the prefix and the
+///   derived identifier. Must contain `{id}`
+///
+/// # Returns
+/// The store ID, or a VssError if the mnemonic is invalid or the template lacks `{id}`.
+///
+/// # Example
+/// ```
+/// let store_id = vss_derive_store_id_with_format(
+///     "bitkit".to_string(),
+///     mnemonic,
+///     None,
+///     "{prefix}-v2-{id}".to_string(),
+/// )?;
+/// ```
+#[uniffi::export]
+pub fn vss_derive_store_id_with_format(
+    prefix: String,
+    mnemonic: String,
+    passphrase: Option<String>,
+    format: String,
+) -> Result<String, VssError> {
+    derive_vss_store_id_with_format(prefix, mnemonic, passphrase, format)
+}
+
+/// Derives a store ID like `vss_derive_store_id`, returning its parts separately.
+///
+/// Useful for building custom store ID schemes from the derived identifier alone.
+/// `full` is exactly the value `vss_derive_store_id` returns for the same inputs.
+///
+/// # Parameters
+/// - `prefix`: A prefix to include in the store ID (e.g., "bitkit_v1_regtest")
+/// - `mnemonic`: BIP39 mnemonic phrase (12, 15, 18, 21 or 24 words)
+/// - `passphrase`: Optional BIP39 passphrase
+///
+/// # Returns
+/// The StoreIdParts, or a VssError if the mnemonic is invalid.
+///
+/// # Example
+/// ```
+/// let parts = vss_derive_store_id_parts(prefix, mnemonic, None)?;
+/// let custom_id = format!("myapp-{}", parts.derived_id);
+/// ```
+#[uniffi::export]
+pub fn vss_derive_store_id_parts(
+    prefix: String,
+    mnemonic: String,
+    passphrase: Option<String>,
+) -> Result<StoreIdParts, VssError> {
+    derive_vss_store_id_parts(prefix, mnemonic, passphrase)
+}
+
+/// Returns the BIP32 derivation paths the client uses.
+///
+/// Purely informational, for security review and for confirming that another
+/// implementation derives the same keys: the VSS root `m/877'` (storage keys), the
+/// LNURL-auth key `m/877'/138'` and the store ID key `m/877'/118'`.
+///
+/// # Example
+/// ```
+/// let info = vss_derivation_info();
+/// assert_eq!(info.lnurl_auth_path, "m/877'/138'");
+/// ```
+#[uniffi::export]
+pub fn vss_derivation_info() -> DerivationInfo {
+    derivation_info()
+}
+
+/// Derives the storage encryption and key obfuscation keys for a seed.
+///
+/// This exposes the exact key derivation used by LNURL-auth clients so that other
+/// implementations can read and write the same encrypted store: the seed is derived
+/// to `m/877'`, and its private key is expanded with HMAC-SHA256 into a data encryption
+/// key (salts `pseudo_random_key`, then `data_encryption_key`) and an obfuscation
+/// master key (salt: the data encryption key followed by `obfuscation_key`).
+///
+/// # Parameters
+/// - `seed_hex`: Hex-encoded 32-byte seed, as used for LNURL-auth client creation
+///
+/// # Returns
+/// The hex-encoded keys, or a VssError if the seed is invalid.
+///
+/// # Example
+/// ```
+/// let keys = vss_derive_storage_keys(seed_hex)?;
+/// println!("Data encryption key: {}", keys.data_encryption_key);
+/// ```
+#[uniffi::export]
+pub fn vss_derive_storage_keys(seed_hex: String) -> Result<StorageKeys, VssError> {
+    let seed = <[u8; 32]>::from_hex(&seed_hex).map_err(|e| VssError::InvalidData {
+        error_details: format!("Invalid seed hex: {}", e),
+    })?;
+
+    derive_storage_keys(seed)
+}
+
+/// Decrypts a stored value obtained out-of-band, without a client.
+///
+/// For tooling and debugging: takes a value exactly as the server stores it, e.g. from a
+/// database dump, and decrypts it with the data encryption key `vss_derive_storage_keys`
+/// derives from the seed. Content type and integrity headers are removed.
+///
+/// # Parameters
+/// - `seed_hex`: Hex-encoded 32-byte seed, as used for LNURL-auth client creation
+/// - `blob`: The serialized `Storable` envelope as held by the server
+///
+/// # Returns
+/// The decrypted value, or a VssError: InvalidData for a bad seed, DecodeError if the blob
+/// isn't an envelope, DecryptError if it wasn't encrypted with this seed's key.
+///
+/// # Example
+/// ```
+/// let value = vss_decrypt_blob(seed_hex, blob_from_db)?;
+/// ```
+#[uniffi::export]
+pub fn vss_decrypt_blob(seed_hex: String, blob: Vec<u8>) -> Result<Vec<u8>, VssError> {
+    let seed = <[u8; 32]>::from_hex(&seed_hex).map_err(|e| VssError::InvalidData {
+        error_details: format!("Invalid seed hex: {}", e),
+    })?;
+
+    decrypt_blob(seed, &blob)
+}
+
+/// Decodes an item's value as a UTF-8 string.
+///
+/// # Parameters
+/// - `item`: An item returned by one of the read functions
+///
+/// # Returns
+/// The value as a string, or VssError::InvalidData if it is not valid UTF-8.
+///
+/// # Example
+/// ```
+/// if let Some(item) = vss_get("profile".to_string()).await? {
+///     let json = vss_item_value_as_string(item)?;
+/// }
+/// ```
+#[uniffi::export]
+pub fn vss_item_value_as_string(item: VssItem) -> Result<String, VssError> {
+    item.value_as_string()
+}
+
+/// Serializes an item as JSON, e.g. for logging or export.
+///
+/// The value is written as an array of byte values so binary data round-trips exactly.
+///
+/// # Parameters
+/// - `item`: The item to serialize
+///
+/// # Returns
+/// The JSON text, or a VssError if serialization fails.
+///
+/// # Example
+/// ```
+/// if let Some(item) = vss_get("profile".to_string()).await? {
+///     println!("{}", vss_item_to_json(item)?);
+/// }
+/// ```
+#[uniffi::export]
+pub fn vss_item_to_json(item: VssItem) -> Result<String, VssError> {
+    item.to_json()
+}
+
+/// Parses an item from JSON produced by `vss_item_to_json`.
+///
+/// # Parameters
+/// - `json`: The JSON text
+///
+/// # Returns
+/// The item, or VssError::InvalidData if the JSON doesn't describe an item.
+///
+/// # Example
+/// ```
+/// let item = vss_item_from_json(saved_json)?;
+/// vss_store(item.key, item.value).await?;
+/// ```
+#[uniffi::export]
+pub fn vss_item_from_json(json: String) -> Result<VssItem, VssError> {
+    VssItem::from_json(&json)
+}
+
+/// Reports whether an operation that failed with `error` is worth retrying.
+///
+/// Uses the same classification as the client's internal retry policy, so host apps
+/// can make consistent retry decisions: network and server failures are retryable,
+/// while not-found, invalid data, conflict, auth and unsupported errors are not.
+/// Errors are only returned after the client's own retries are exhausted, so callers
+/// should retry retryable errors with their own, longer backoff.
+///
+/// # Parameters
+/// - `error`: An error returned by another function
+///
+/// # Returns
+/// `true` if retrying may succeed
+///
+/// # Example
+/// ```
+/// if let Err(error) = vss_store(key, value).await {
+///     if vss_error_is_retryable(error) {
+///         schedule_retry();
+///     }
+/// }
+/// ```
+#[uniffi::export]
+pub fn vss_error_is_retryable(error: VssError) -> bool {
+    error.is_retryable()
+}
+
+/// Sets whether error messages hide key names, for apps whose keys contain sensitive
+/// identifiers that shouldn't reach logs.
+///
+/// When enabled, messages name a key by `redacted-` and the first 16 hex digits of its
+/// SHA-256 hash, which stays the same across errors so log lines can still be correlated.
+/// Only the messages change: structured fields such as `DecodeError::key` keep the real
+/// key. The setting applies to all clients in the process and is off by default.
+///
+/// # Parameters
+/// - `redact`: Whether to redact keys in error messages
+///
+/// # Example
+/// ```
+/// vss_set_redact_keys_in_errors(true);
+/// ```
+#[uniffi::export]
+pub fn vss_set_redact_keys_in_errors(redact: bool) {
+    set_redact_keys_in_errors(redact);
+}
+
+/// Shuts down the VSS client and clears the global client state.
+///
+/// This function is optional but recommended for clean shutdown in applications
+/// that want to explicitly release resources. A `vss_rotate_encryption` still running
+/// doesn't install its rotated client afterwards.
 ///
 /// # Example
 /// ```
 /// vss_shutdown_client();
 /// ```
+#[cfg(feature = "global-client")]
 #[uniffi::export]
 pub fn vss_shutdown_client() {
-    if let Some(client_storage) = VSS_CLIENT.get() {
-        let mut guard = client_storage.lock().unwrap();
-        *guard = None;
-    }
+    take_client();
+}
+
+/// Shuts down the VSS client after writing pending values and letting running operations
+/// finish.
+///
+/// The global client is cleared first, like `vss_shutdown_client`, so operations started
+/// after the call fail right away instead of prolonging the wait. Then the values scheduled
+/// with `vss_store_debounced` are written and operations started before the call are
+/// waited for, both within `timeout_ms`. Values still pending or requests still running
+/// when the timeout elapses are abandoned.
+///
+/// # Parameters
+/// - `timeout_ms`: How long to wait in total, in milliseconds
+///
+/// # Returns
+/// Ok(()) once everything completed, a TimeoutError if the timeout elapsed first, or
+/// the VssError of a failed pending write
+///
+/// # Example
+/// ```
+/// vss_shutdown_client_graceful(5_000).await?;
+/// ```
+#[cfg(feature = "global-client")]
+#[uniffi::export]
+pub async fn vss_shutdown_client_graceful(timeout_ms: u64) -> Result<(), VssError> {
+    // Not counted as in flight itself, so waiting for idle doesn't wait on this call
+    execute_untracked!(async move {
+        let timeout = std::time::Duration::from_millis(timeout_ms);
+        let timed_out = || VssError::TimeoutError {
+            error_details: format!("Shutdown did not complete within {} ms", timeout_ms),
+        };
+        let client = take_client();
+        let drain = async {
+            if let Some(client) = client {
+                client.flush().await?;
+            }
+            wait_until_idle().await;
+            Ok(())
+        };
+
+        tokio::time::timeout(timeout, drain).await.unwrap_or_else(|_| Err(timed_out()))
+    })
 }
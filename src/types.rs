@@ -1,10 +1,45 @@
+use crate::errors::{display_key, VssError};
 use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 
 #[derive(Debug, Clone, uniffi::Record, Serialize, Deserialize)]
 pub struct VssItem {
     pub key: String,
     pub value: Vec<u8>,
     pub version: i64,
+    /// Last modification time in milliseconds since the Unix epoch. Always None for now:
+    /// the VSS protocol doesn't report modification times yet.
+    #[uniffi(default = None)]
+    #[serde(default)]
+    pub last_modified_ms: Option<i64>,
+    /// Content type stored with the value, None for values stored without one.
+    #[uniffi(default = None)]
+    #[serde(default)]
+    pub content_type: Option<String>,
+}
+
+impl VssItem {
+    /// Decodes the value as UTF-8 text.
+    pub fn value_as_string(&self) -> Result<String, VssError> {
+        String::from_utf8(self.value.clone()).map_err(|e| VssError::InvalidData {
+            error_details: format!("Value of key '{}' is not valid UTF-8: {}", display_key(&self.key), e),
+        })
+    }
+
+    /// Serializes the item as JSON, with the value as an array of byte values.
+    pub fn to_json(&self) -> Result<String, VssError> {
+        serde_json::to_string(self).map_err(|e| VssError::InvalidData {
+            error_details: format!("Failed to serialize item '{}': {}", display_key(&self.key), e),
+        })
+    }
+
+    /// Parses an item from JSON produced by `to_json`.
+    pub fn from_json(json: &str) -> Result<Self, VssError> {
+        serde_json::from_str(json).map_err(|e| VssError::InvalidData {
+            error_details: format!("Invalid item JSON: {}", e),
+        })
+    }
 }
 
 #[derive(Debug, Clone, uniffi::Record, Serialize, Deserialize)]
@@ -13,19 +48,342 @@ pub struct KeyValue {
     pub value: Vec<u8>,
 }
 
+/// A key to write conditionally, with the version the server must hold it at.
+#[derive(Debug, Clone, uniffi::Record)]
+pub struct VersionedKeyValue {
+    pub key: String,
+    pub value: Vec<u8>,
+    /// 0 if the key must not exist yet, -1 to write it unconditionally
+    pub expected_version: i64,
+}
+
+/// The result of `vss_store_detailed`.
+#[derive(Debug, Clone, uniffi::Record)]
+pub struct StoreOutcome {
+    pub item: VssItem,
+    /// Whether the key was created rather than updated
+    pub created: bool,
+}
+
+/// One page of items from a paginated listing.
+#[derive(Debug, Clone, uniffi::Record)]
+pub struct ListPage {
+    pub items: Vec<VssItem>,
+    /// Token to pass to fetch the next page, None when this is the last page.
+    pub next_page_token: Option<String>,
+}
+
+/// One page of keys and versions from a paginated key listing.
 #[derive(Debug, Clone, uniffi::Record, Serialize, Deserialize)]
 pub struct ListKeyVersionsResponse {
     pub key_versions: Vec<KeyVersion>,
+    /// Token to pass to fetch the next page, None when this is the last page.
+    pub next_page_token: Option<String>,
 }
 
 #[derive(Debug, Clone, uniffi::Record, Serialize, Deserialize)]
 pub struct KeyVersion {
     pub key: String,
     pub version: i64,
+    /// Last modification time in milliseconds since the Unix epoch. Always None for now:
+    /// the VSS protocol doesn't report modification times yet.
+    #[uniffi(default = None)]
+    #[serde(default)]
+    pub last_modified_ms: Option<i64>,
 }
 
 #[derive(Debug, Clone, uniffi::Enum)]
 pub enum VssFilterType {
     Prefix,
     Exact,
-}
\ No newline at end of file
+}
+
+/// A certificate or public key the server must present, for clients created with pinning.
+#[derive(Debug, Clone, uniffi::Enum)]
+pub enum CertificatePin {
+    /// A DER or PEM encoded certificate. PEM input may hold several certificates.
+    Certificate { encoded: Vec<u8> },
+    /// The SHA-256 hash of a DER encoded SubjectPublicKeyInfo, as in HPKP's `pin-sha256`
+    SpkiSha256 { hash: Vec<u8> },
+}
+
+/// A key that could not be read, with the reason.
+#[derive(Debug, Clone, uniffi::Record)]
+pub struct KeyError {
+    pub key: String,
+    pub error: String,
+}
+
+/// The outcome of a batch read that tolerates per-key failures.
+#[derive(Debug, Clone, uniffi::Record)]
+pub struct BatchGetResult {
+    pub items: Vec<VssItem>,
+    pub failures: Vec<KeyError>,
+}
+
+/// The outcome of a chunked batch write, which stops at the first chunk that fails.
+#[derive(Debug, Clone, uniffi::Record)]
+pub struct BatchPutResult {
+    /// The items of the chunks written before any failure, all items on success.
+    pub written: Vec<VssItem>,
+    /// Index of the chunk whose transaction failed, None if every chunk was written.
+    /// Chunks from this one on were not written.
+    pub failed_chunk_index: Option<u32>,
+    /// Why the chunk failed, None on success.
+    pub error: Option<String>,
+}
+
+/// Cancels the batch operations it's passed to, such as `get_many_partial`.
+///
+/// Cancelling can't be undone: operations running with the handle stop with
+/// `VssError::Cancelled`, and so does any later operation given the same handle.
+#[derive(Debug, Default, uniffi::Object)]
+pub struct VssCancelHandle {
+    cancelled: AtomicBool,
+}
+
+#[uniffi::export]
+impl VssCancelHandle {
+    /// Creates a handle that isn't cancelled yet.
+    #[uniffi::constructor]
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    /// Stops the operations using this handle at their next check.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// Whether `cancel` was called.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+/// What the connected VSS server supports, as far as it can be determined.
+///
+/// VSS servers don't publish their version or limits, so fields that can't be
+/// observed are None.
+#[derive(Debug, Clone, uniffi::Record)]
+pub struct ServerInfo {
+    pub version: Option<String>,
+    pub supports_global_version: bool,
+    pub max_batch_size: Option<u32>,
+}
+
+/// Aggregate figures over the keys of a store, or of a prefix within it.
+#[derive(Debug, Clone, uniffi::Record)]
+pub struct StoreStats {
+    pub key_count: u64,
+    /// Sum of the stored (encrypted) value sizes in bytes
+    pub total_bytes: u64,
+    /// Highest key version, 0 when there are no keys
+    pub max_version: i64,
+}
+
+/// The keys on which two stores disagree, each list sorted by key.
+#[derive(Debug, Clone, uniffi::Record)]
+pub struct StoreDiff {
+    /// Keys present in the store of the client `diff_against` was called on but not in the other
+    pub only_in_self: Vec<String>,
+    /// Keys present in the store passed to `diff_against` but not in the client's own
+    pub only_in_other: Vec<String>,
+    /// Keys present in both stores whose decrypted values differ
+    pub differing: Vec<String>,
+}
+
+/// The components of a derived store ID. `full` is `"{prefix}_{derived_id}"`.
+#[derive(Debug, Clone, uniffi::Record)]
+pub struct StoreIdParts {
+    pub prefix: String,
+    pub derived_id: String,
+    pub full: String,
+}
+
+/// BIP32 derivation paths, from the master key of the seed, used by the client.
+#[derive(Debug, Clone, uniffi::Record)]
+pub struct DerivationInfo {
+    /// The VSS root key, whose private key the storage keys are derived from: `m/877'`
+    pub vss_root_path: String,
+    /// The key LNURL-auth signs with: `m/877'/138'`
+    pub lnurl_auth_path: String,
+    /// The key store IDs are hashed from: `m/877'/118'`
+    pub store_id_path: String,
+}
+
+/// Hex-encoded keys used to encrypt values and obfuscate keys in a store.
+#[derive(Debug, Clone, uniffi::Record)]
+pub struct StorageKeys {
+    pub data_encryption_key: String,
+    pub obfuscation_key: String,
+}
+
+/// Computes the new value of a key in `vss_update`, implemented by the host app.
+///
+/// May be called several times for one update when concurrent writers conflict, so it
+/// should have no side effects besides returning the new value.
+#[uniffi::export(callback_interface)]
+pub trait ValueUpdater: Send + Sync {
+    /// Returns the value to store given the current one, None if the key doesn't exist.
+    fn update(&self, current: Option<Vec<u8>>) -> Vec<u8>;
+}
+
+/// Receives the progress of long-running operations such as `vss_export_ndjson`,
+/// implemented by the host app.
+///
+/// Called on the thread running the operation, so it should return quickly, e.g. by
+/// posting the numbers to the UI thread.
+#[uniffi::export(callback_interface)]
+pub trait VssProgress: Send + Sync {
+    /// Reports that `done` of `total` items have been processed.
+    fn on_progress(&self, done: u64, total: u64);
+}
+
+/// Told about each retry of a request to the server, implemented by the host app to show
+/// e.g. "retrying (3/10)" during a slow sync.
+///
+/// Called on the thread running the request, right before the backoff delay, so it should
+/// return quickly.
+#[uniffi::export(callback_interface)]
+pub trait VssRetryListener: Send + Sync {
+    /// Reports that the `operation` request (the VSS endpoint, e.g. "getObject") failed and
+    /// will be sent again as attempt number `attempt` after `next_delay_ms` milliseconds.
+    fn on_retry(&self, operation: String, attempt: u32, next_delay_ms: u64);
+}
+
+/// Client-side behaviour that can be tuned when constructing a client.
+#[derive(Debug, Clone, uniffi::Record)]
+pub struct VssClientConfig {
+    /// Maximum length of a user key in bytes, measured before obfuscation.
+    #[uniffi(default = 1024)]
+    pub max_key_length: u32,
+    /// Make every request exactly once, failing fast instead of retrying transient errors.
+    #[uniffi(default = false)]
+    pub disable_retries: bool,
+    /// Maximum number of items sent in one batch write; larger batches are split.
+    #[uniffi(default = 1000)]
+    pub max_batch_items: u32,
+    /// Maximum encoded size in bytes of one batch write; larger batches are split.
+    #[uniffi(default = 4194304)]
+    pub max_batch_bytes: u64,
+    /// Obfuscate keys when encryption is on. Turning it off keeps values encrypted but
+    /// stores keys in plaintext, so the server operator can read every key name.
+    #[uniffi(default = true)]
+    pub obfuscate_keys: bool,
+    /// Store keys longer than `max_plain_key_len` under their SHA-256 hash. Hashed keys
+    /// are listed in their hashed form; the original key can't be recovered from them.
+    #[uniffi(default = false)]
+    pub hash_long_keys: bool,
+    /// Key length in bytes, including any view prefix, above which keys are hashed.
+    #[uniffi(default = 256)]
+    pub max_plain_key_len: u32,
+    /// Probe the server while constructing a server-backed client, so an unreachable
+    /// server or rejected auth fails construction instead of the first operation.
+    #[uniffi(default = false)]
+    pub validate_on_init: bool,
+    /// Sent as the `x-request-id` header of every request to a server, followed by `-` and
+    /// a sequence number that increases with each request, for distributed tracing.
+    #[uniffi(default = None)]
+    pub correlation_id: Option<String>,
+    /// Sent as the `User-Agent` header of every request to a server. None sends
+    /// `vss-rust-client-ffi/<crate version>`.
+    #[uniffi(default = None)]
+    pub user_agent: Option<String>,
+    /// Largest stored (encrypted) value in bytes the client accepts from the server.
+    /// Larger values are rejected before decoding. None for no limit.
+    #[uniffi(default = None)]
+    pub max_value_bytes: Option<u64>,
+    /// Store a SHA-256 checksum of each written value inside its encrypted envelope.
+    /// Checksums are verified on every read whether or not this is set, failing with
+    /// `VssError::IntegrityError` on a mismatch; values written without one aren't checked.
+    #[uniffi(default = false)]
+    pub verify_integrity: bool,
+    /// Accept an empty base URL when constructing a server-backed client, for test harnesses
+    /// that never reach a server. Every request from such a client fails.
+    #[uniffi(default = false)]
+    pub allow_empty_base_url: bool,
+    /// Time in milliseconds the server may take to accept a connection, failing with
+    /// `VssError::ConnectionError` when exceeded. None for no limit.
+    ///
+    /// Clients without LNURL-auth or `correlation_id` apply it to each connection attempt,
+    /// TLS handshake included. `vss-client` doesn't accept a preconfigured HTTP client for
+    /// the others, so for them it is checked with a TCP connect before requests that may
+    /// open a new connection instead, which doesn't cover the TLS handshake and is skipped
+    /// when a proxy is set in `proxy_url` or the environment.
+    #[uniffi(default = None)]
+    pub connect_timeout_ms: Option<u64>,
+    /// Time in milliseconds a request may take, including its retries, failing with
+    /// `VssError::TimeoutError` when exceeded. Clients without LNURL-auth or `correlation_id`
+    /// also apply it to each attempt on its own. None for no limit.
+    #[uniffi(default = None)]
+    pub read_timeout_ms: Option<u64>,
+    /// Round-trip a sample key through the key obfuscator while constructing the client,
+    /// failing with `VssError::ConnectionError` if it doesn't come back unchanged.
+    #[uniffi(default = false)]
+    pub verify_key_obfuscation_on_init: bool,
+    /// Keys requested per page by operations that page through a listing internally, such
+    /// as `list`, `list_keys` and `clear_store`. Pages requested explicitly, e.g. with
+    /// `list_page`, are unaffected. None uses `DEFAULT_PAGE_SIZE` (1000). Servers may return
+    /// smaller pages than requested.
+    #[uniffi(default = None)]
+    pub default_page_size: Option<u32>,
+    /// Reject every write, including deletes, imports and key rotation, with
+    /// `VssError::Unsupported { feature: "read_only" }` before anything is sent to the
+    /// server. Reads, listings and exports work as usual.
+    #[uniffi(default = false)]
+    pub read_only: bool,
+    /// Largest total encoded size in bytes of one batch write such as
+    /// `put_with_key_prefix`, counted over all its chunks. Larger batches fail with
+    /// `VssError::InvalidData` before anything is sent, unlike `max_batch_bytes`, which
+    /// splits them. None for no limit.
+    #[uniffi(default = None)]
+    pub max_request_bytes: Option<u64>,
+    /// Proxy for every request to the server, as an `http://`, `https://`, `socks5://` or
+    /// `socks5h://` URL, replacing the proxy environment variables. Use `socks5h://` for Tor
+    /// so hostnames are resolved by the proxy. An invalid URL fails client construction with
+    /// `VssError::ConnectionError`. Not supported with LNURL-auth or `correlation_id`.
+    #[uniffi(default = None)]
+    pub proxy_url: Option<String>,
+    /// Advanced: HKDF salt for the pseudo-random key that the storage keys are derived
+    /// from. The defaults match LDK's VSS conventions. Changing any of the salts derives
+    /// different keys, making everything already in the store unreadable.
+    #[uniffi(default = "pseudo_random_key")]
+    pub hkdf_prk_salt: String,
+    /// Advanced: HKDF salt for the data encryption key. See `hkdf_prk_salt`.
+    #[uniffi(default = "data_encryption_key")]
+    pub hkdf_data_encryption_salt: String,
+    /// Advanced: HKDF salt for the key obfuscation key. See `hkdf_prk_salt`.
+    #[uniffi(default = "obfuscation_key")]
+    pub hkdf_obfuscation_salt: String,
+}
+
+impl Default for VssClientConfig {
+    fn default() -> Self {
+        Self {
+            max_key_length: 1024,
+            disable_retries: false,
+            max_batch_items: 1000,
+            max_batch_bytes: 4 * 1024 * 1024,
+            obfuscate_keys: true,
+            hash_long_keys: false,
+            max_plain_key_len: 256,
+            validate_on_init: false,
+            correlation_id: None,
+            user_agent: None,
+            max_value_bytes: None,
+            verify_integrity: false,
+            allow_empty_base_url: false,
+            connect_timeout_ms: None,
+            read_timeout_ms: None,
+            verify_key_obfuscation_on_init: false,
+            default_page_size: None,
+            read_only: false,
+            max_request_bytes: None,
+            proxy_url: None,
+            hkdf_prk_salt: "pseudo_random_key".to_string(),
+            hkdf_data_encryption_salt: "data_encryption_key".to_string(),
+            hkdf_obfuscation_salt: "obfuscation_key".to_string(),
+        }
+    }
+}